@@ -0,0 +1,8 @@
+#![no_main]
+
+use jupiter_core::amms::accounts::load_clock;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = load_clock(data);
+});
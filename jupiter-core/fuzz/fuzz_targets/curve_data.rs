@@ -0,0 +1,17 @@
+#![no_main]
+
+use jupiter_core::amms::accounts::{CurveData, CURVE_DATA_ACCOUNT_SIZE, CURVE_DATA_DISCRIMINATOR};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = CurveData::load(data);
+
+    // Only accounts of the exact layout size decode, so also decode the input as the start of one
+    let mut account_data = vec![0u8; CURVE_DATA_ACCOUNT_SIZE];
+    account_data[0..8].copy_from_slice(&CURVE_DATA_DISCRIMINATOR);
+    let len = data.len().min(CURVE_DATA_ACCOUNT_SIZE - 8);
+    account_data[8..8 + len].copy_from_slice(&data[..len]);
+    if let Ok(decoded) = CurveData::load(&account_data) {
+        assert_eq!(CurveData::load(&decoded.to_account_data()).unwrap(), decoded);
+    }
+});
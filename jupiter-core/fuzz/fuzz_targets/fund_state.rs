@@ -0,0 +1,17 @@
+#![no_main]
+
+use jupiter_core::amms::accounts::{FundState, FUND_STATE_ACCOUNT_SIZE, FUND_STATE_DISCRIMINATOR};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = FundState::load(data);
+
+    // Only accounts of the exact layout size decode, so also decode the input as the start of one
+    let mut account_data = vec![0u8; FUND_STATE_ACCOUNT_SIZE];
+    account_data[0..8].copy_from_slice(&FUND_STATE_DISCRIMINATOR);
+    let len = data.len().min(FUND_STATE_ACCOUNT_SIZE - 8);
+    account_data[8..8 + len].copy_from_slice(&data[..len]);
+    if let Ok(decoded) = FundState::load(&account_data) {
+        assert_eq!(FundState::load(&decoded.to_account_data()).unwrap(), decoded);
+    }
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use jupiter_core::amms::accounts::{OraclePrice, TokenList};
+use jupiter_core::amms::oracles::multi_feed::MultiFeedPolicy;
+use libfuzzer_sys::fuzz_target;
+use solana_sdk::clock::Clock;
+
+// The first 20 bytes pick the token settings and the clock, the rest is the oracle account
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 20 {
+        return;
+    }
+    let mut token_settings = TokenList::empty().list[0];
+    token_settings.oracle_type = data[0];
+    token_settings.oracle_index = data[1];
+    token_settings.oracle_confidence_pct = data[2];
+    token_settings.fixed_confidence_bps = data[3];
+    let clock = Clock {
+        slot: u64::from_le_bytes(data[4..12].try_into().unwrap()),
+        unix_timestamp: i64::from_le_bytes(data[12..20].try_into().unwrap()),
+        ..Clock::default()
+    };
+
+    for multi_feed_policy in [MultiFeedPolicy::Disabled, MultiFeedPolicy::TimeBasedConfidence] {
+        if let Ok(price) = OraclePrice::load(&data[20..], token_settings, &clock, multi_feed_policy) {
+            assert!(price.sell_price <= price.avg_price && price.avg_price <= price.buy_price);
+        }
+    }
+});
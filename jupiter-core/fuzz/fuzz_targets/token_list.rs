@@ -0,0 +1,17 @@
+#![no_main]
+
+use jupiter_core::amms::accounts::{TokenList, TOKEN_LIST_ACCOUNT_SIZE, TOKEN_LIST_DISCRIMINATOR};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = TokenList::load(data);
+
+    // Only accounts of the exact layout size decode, so also decode the input as the start of one
+    let mut account_data = vec![0u8; TOKEN_LIST_ACCOUNT_SIZE];
+    account_data[0..8].copy_from_slice(&TOKEN_LIST_DISCRIMINATOR);
+    let len = data.len().min(TOKEN_LIST_ACCOUNT_SIZE - 8);
+    account_data[8..8 + len].copy_from_slice(&data[..len]);
+    if let Ok(decoded) = TokenList::load(&account_data) {
+        assert_eq!(TokenList::load(&decoded.to_account_data()).unwrap(), decoded);
+    }
+});
@@ -1,11 +1,32 @@
 use anchor_lang::prelude::*;
 use std::convert::TryInto;
-use anyhow::{Result, Error};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::amms::error::SymmetryError;
+use crate::amms::serialization::{array, pubkey_base58};
+use crate::amms::oracles::multi_feed::{MultiFeedOracleAccount, MultiFeedPolicy};
+use crate::amms::oracles::pyth::{PythPriceAccount, PythPriceStatus};
+use crate::amms::oracles::pyth_pull::{PythPriceUpdateAccount, PythVerificationLevel};
 
 pub const FUND_STATE_ACCOUNT_SIZE: usize = 10208;
 pub const TOKEN_LIST_ACCOUNT_SIZE: usize = 39816;
 pub const CURVE_DATA_ACCOUNT_SIZE: usize = 64008;
-pub const ORACLE_ACCOUNT_SIZE: [usize; 2] = [3312, 809];
+pub const ORACLE_ACCOUNT_SIZE: [usize; 3] = [3312, 809, 134];
+pub const CLOCK_ACCOUNT_SIZE: usize = 40;
+
+/// Anchor discriminators, the first 8 bytes of `sha256("account:<AccountName>")`, written by the encoders.
+/// The account names are assumed to match the types decoded here and have not been checked against
+/// mainnet accounts, so the decoders don't reject accounts on them.
+pub const FUND_STATE_DISCRIMINATOR: [u8; 8] = [3, 254, 145, 43, 146, 96, 162, 104];
+pub const TOKEN_LIST_DISCRIMINATOR: [u8; 8] = [145, 167, 153, 173, 5, 187, 157, 150];
+pub const CURVE_DATA_DISCRIMINATOR: [u8; 8] = [68, 250, 194, 234, 191, 224, 106, 12];
+
+/// Account sizes of every known layout version.
+pub const FUND_STATE_LAYOUTS: [(LayoutVersion, usize); 1] = [(LayoutVersion::V1, FUND_STATE_ACCOUNT_SIZE)];
+pub const TOKEN_LIST_LAYOUTS: [(LayoutVersion, usize); 1] = [(LayoutVersion::V1, TOKEN_LIST_ACCOUNT_SIZE)];
+pub const CURVE_DATA_LAYOUTS: [(LayoutVersion, usize); 1] = [(LayoutVersion::V1, CURVE_DATA_ACCOUNT_SIZE)];
+pub const PYTH_PULL_MAX_AGE_SECONDS: i64 = 10;
 
 pub const MAX_TOKENS_IN_ASSET_POOL: usize = 100;
 pub const NUM_TOKENS_IN_FUND: usize = 20;
@@ -16,6 +37,25 @@ pub const BPS_DIVIDER: u64 = 10000;
 pub const WEIGHT_MULTIPLIER: u64 = 10000;
 pub const FUND_LP_DISABLED: u64 = 1;
 pub const LP_DISABLED: u8 = 0;
+pub const TOKEN_NOT_LIVE: u8 = 0;
+
+/// Layout of the Symmetry program accounts. A program upgrade that resizes an account gets a new
+/// version, so accounts of an unknown size are rejected instead of being decoded with stale offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutVersion {
+    V1,
+}
+
+impl LayoutVersion {
+    /// Matches the size of a Symmetry account against `layouts`. With `V1` the only version this is
+    /// a size whitelist, no offsets depend on the returned version yet.
+    pub fn detect(account: &'static str, account_data: &[u8], layouts: &[(LayoutVersion, usize)]) -> Result<LayoutVersion> {
+        layouts.iter()
+            .find(|(_, size)| *size == account_data.len())
+            .map(|(version, _)| *version)
+            .ok_or_else(|| SymmetryError::UnsupportedLayout { account, size: account_data.len() }.into())
+    }
+}
 
 pub fn mul_div(a: u64, b: u64, c: u64) -> u64 {
     match c {
@@ -25,9 +65,13 @@ pub fn mul_div(a: u64, b: u64, c: u64) -> u64 {
     }
 }
 
-#[derive(Clone, Copy)]
+/// The fields of the Symmetry `FundState` account needed for quoting, decoded from fixed offsets.
+/// The program IDL is not checked into this repo, so the remaining fields are not decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FundState {
+    #[serde(with = "pubkey_base58")]
     pub manager: Pubkey,
+    #[serde(with = "pubkey_base58")]
     pub host_pubkey: Pubkey,
     pub num_of_tokens: u64,
     pub current_comp_token: [u64; NUM_TOKENS_IN_FUND],
@@ -42,44 +86,125 @@ pub struct FundState {
 impl FundState {
     #[inline]
     pub fn load<'a>(account_data: &[u8]) -> Result<FundState> {
-        if account_data.len() != FUND_STATE_ACCOUNT_SIZE {
-            return Err(Error::msg("Wrong account size for FundState"));
-        }
+        let view = FundStateView::new(account_data)?;
         let mut current_comp_token: [u64; NUM_TOKENS_IN_FUND] = [0 as u64; NUM_TOKENS_IN_FUND];
         let mut current_comp_amount: [u64; NUM_TOKENS_IN_FUND] = [0 as u64; NUM_TOKENS_IN_FUND];
         let mut target_weight: [u64; NUM_TOKENS_IN_FUND] = [0 as u64; NUM_TOKENS_IN_FUND];
         for i in 0..NUM_TOKENS_IN_FUND {
-            current_comp_token[i] = u64::from_le_bytes(account_data[(176 + i*8)..(184 + i*8)].try_into().unwrap_or_default());
-            current_comp_amount[i] = u64::from_le_bytes(account_data[(336 + i*8)..(344 + i*8)].try_into().unwrap_or_default());
-            target_weight[i] = u64::from_le_bytes(account_data[(656 + i*8)..(664 + i*8)].try_into().unwrap_or_default());
+            current_comp_token[i] = view.current_comp_token(i);
+            current_comp_amount[i] = view.current_comp_amount(i);
+            target_weight[i] = view.target_weight(i);
         }
-        let num_of_tokens = u64::from_le_bytes(account_data[168..176].try_into().unwrap_or_default());
-        let weight_sum = u64::from_le_bytes(account_data[816..824].try_into().unwrap_or_default());
-        let rebalance_threshold = u64::from_le_bytes(account_data[1024..1032].try_into().unwrap_or_default());
-        let lp_offset_threshold = u64::from_le_bytes(account_data[1040..1048].try_into().unwrap_or_default());
-        let lp_disabled = u64::from_le_bytes(account_data[9432..9440].try_into().unwrap_or_default());
         Ok(FundState {
-            manager: Pubkey::new_from_array(account_data[16..48].try_into().unwrap_or_default()),
-            host_pubkey: Pubkey::new_from_array(account_data[128..160].try_into().unwrap_or_default()),
-            num_of_tokens,
+            manager: view.manager(),
+            host_pubkey: view.host_pubkey(),
+            num_of_tokens: view.num_of_tokens(),
             current_comp_token,
             current_comp_amount,
             target_weight,
-            weight_sum,
-            rebalance_threshold,
-            lp_offset_threshold,
-            lp_disabled,
+            weight_sum: view.weight_sum(),
+            rebalance_threshold: view.rebalance_threshold(),
+            lp_offset_threshold: view.lp_offset_threshold(),
+            lp_disabled: view.lp_disabled(),
         })
     }
+
+    /// Serializes the decoded fields into a `FundState` account, other fields are left zeroed.
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut account_data = vec![0u8; FUND_STATE_ACCOUNT_SIZE];
+        account_data[0..8].copy_from_slice(&FUND_STATE_DISCRIMINATOR);
+        write_pubkey(&mut account_data, 16, &self.manager);
+        write_pubkey(&mut account_data, 128, &self.host_pubkey);
+        write_u64(&mut account_data, 168, self.num_of_tokens);
+        for i in 0..NUM_TOKENS_IN_FUND {
+            write_u64(&mut account_data, 176 + i * 8, self.current_comp_token[i]);
+            write_u64(&mut account_data, 336 + i * 8, self.current_comp_amount[i]);
+            write_u64(&mut account_data, 656 + i * 8, self.target_weight[i]);
+        }
+        write_u64(&mut account_data, 816, self.weight_sum);
+        write_u64(&mut account_data, 1024, self.rebalance_threshold);
+        write_u64(&mut account_data, 1040, self.lp_offset_threshold);
+        write_u64(&mut account_data, 9432, self.lp_disabled);
+        account_data
+    }
+}
+
+fn read_u64(account_data: &[u8], offset: usize) -> u64 {
+    account_data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or_default()
+}
+
+fn write_u64(account_data: &mut [u8], offset: usize, value: u64) {
+    account_data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
 }
 
+fn write_pubkey(account_data: &mut [u8], offset: usize, value: &Pubkey) {
+    account_data[offset..offset + 32].copy_from_slice(&value.to_bytes());
+}
+
+fn read_pubkey(account_data: &[u8], offset: usize) -> Pubkey {
+    account_data.get(offset..offset + 32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(Pubkey::new_from_array)
+        .unwrap_or_default()
+}
+
+/// Reads `FundState` fields straight from the account bytes, without copying them.
 #[derive(Clone, Copy)]
+pub struct FundStateView<'a> {
+    account_data: &'a [u8],
+}
+
+impl<'a> FundStateView<'a> {
+    pub fn new(account_data: &'a [u8]) -> Result<FundStateView<'a>> {
+        LayoutVersion::detect("FundState", account_data, &FUND_STATE_LAYOUTS)?;
+        let view = FundStateView { account_data };
+        if view.num_of_tokens() > NUM_TOKENS_IN_FUND as u64 {
+            return Err(SymmetryError::TooManyTokens {
+                account: "FundState",
+                num_tokens: view.num_of_tokens(),
+                max: NUM_TOKENS_IN_FUND,
+            }.into());
+        }
+        Ok(view)
+    }
+
+    /// Skips the size check, for bytes that already went through `new`. Reads never panic either way.
+    pub(crate) fn new_unchecked(account_data: &'a [u8]) -> FundStateView<'a> {
+        FundStateView { account_data }
+    }
+
+    pub fn manager(&self) -> Pubkey { read_pubkey(self.account_data, 16) }
+    pub fn host_pubkey(&self) -> Pubkey { read_pubkey(self.account_data, 128) }
+    pub fn num_of_tokens(&self) -> u64 { read_u64(self.account_data, 168) }
+    pub fn current_comp_token(&self, index: usize) -> u64 { self.fund_token_field(176, index) }
+    pub fn current_comp_amount(&self, index: usize) -> u64 { self.fund_token_field(336, index) }
+    pub fn target_weight(&self, index: usize) -> u64 { self.fund_token_field(656, index) }
+    pub fn weight_sum(&self) -> u64 { read_u64(self.account_data, 816) }
+    pub fn rebalance_threshold(&self) -> u64 { read_u64(self.account_data, 1024) }
+    pub fn lp_offset_threshold(&self) -> u64 { read_u64(self.account_data, 1040) }
+    pub fn lp_disabled(&self) -> u64 { read_u64(self.account_data, 9432) }
+
+    fn fund_token_field(&self, offset: usize, index: usize) -> u64 {
+        if index >= NUM_TOKENS_IN_FUND {
+            return 0;
+        }
+        read_u64(self.account_data, offset + index * 8)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenSettings {                                      // 199 bytes
+    #[serde(with = "pubkey_base58")]
     pub token_mint: Pubkey,                                     // 32 bytes
     pub decimals: u8,                                           // 1 byte
     pub coingecko_id: [u8; 30],                                 // 30 bytes
+    #[serde(with = "pubkey_base58")]
     pub pda_token_account: Pubkey,                              // 32 bytes
     pub oracle_type: u8,                                        // 1 byte
+    #[serde(with = "pubkey_base58")]
     pub oracle_account: Pubkey,                                 // 32 bytes
     pub oracle_index: u8,                                       // 1 byte
     pub oracle_confidence_pct: u8,                              // 1 byte
@@ -89,136 +214,290 @@ pub struct TokenSettings {                                      // 199 bytes
     pub is_live: u8,                                            // 1 byte
     pub lp_on: u8,                                              // 1 byte
     pub use_curve_data: u8,                                     // 1 byte
+    #[serde(with = "array")]
     pub additional_data: [u8; 63],                              // 64 bytes
     pub oracle_price: OraclePrice,
 }
 
-#[derive(Clone, Copy)]
+impl TokenSettings {
+    /// Writes the 199 byte on-chain layout of the token into `slice`.
+    fn write(&self, slice: &mut [u8]) {
+        write_pubkey(slice, 0, &self.token_mint);
+        slice[32] = self.decimals;
+        slice[33..63].copy_from_slice(&self.coingecko_id);
+        write_pubkey(slice, 63, &self.pda_token_account);
+        slice[95] = self.oracle_type;
+        write_pubkey(slice, 96, &self.oracle_account);
+        slice[128] = self.oracle_index;
+        slice[129] = self.oracle_confidence_pct;
+        slice[130] = self.fixed_confidence_bps;
+        slice[131] = self.token_swap_fee_after_tw_bps;
+        slice[132] = self.token_swap_fee_before_tw_bps;
+        slice[133] = self.is_live;
+        slice[134] = self.lp_on;
+        slice[135] = self.use_curve_data;
+        slice[136..199].copy_from_slice(&self.additional_data);
+    }
+
+    /// Coingecko id of the token, empty if it's unset or not valid utf-8.
+    pub fn coingecko_id(&self) -> &str {
+        let len = self.coingecko_id.iter().position(|&c| c == 0).unwrap_or(self.coingecko_id.len());
+        std::str::from_utf8(&self.coingecko_id[..len]).unwrap_or_default()
+    }
+}
+
+/// How swap fees are split, in percent of the total fee. The fund keeps the rest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSplit {
+    pub symmetry_pct: u8,
+    pub host_pct: u8,
+    pub manager_pct: u8,
+}
+
+impl FeeSplit {
+    fn from_additional_data(additional_data: &[u8; 63]) -> FeeSplit {
+        FeeSplit {
+            symmetry_pct: additional_data[60],
+            host_pct: additional_data[61],
+            manager_pct: additional_data[62],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenList {                                          // 39808 bytes
     pub num_tokens: u64,                                        // 8 bytes
+    #[serde(with = "array")]
     pub list: [TokenSettings; MAX_TOKENS_IN_ASSET_POOL],        // 39800 bytes
 }
 
 impl TokenList {
     #[inline]
-    pub fn load<'a>(account_data: &Vec<u8>) -> Result<TokenList> {
-        if account_data.len() != TOKEN_LIST_ACCOUNT_SIZE {
-            return Err(Error::msg("Wrong account size for TokenList"));
+    pub fn load<'a>(account_data: &[u8]) -> Result<TokenList> {
+        let view = TokenListView::new(account_data)?;
+        let mut list = TokenList::empty().list;
+        for (i, token_settings) in list.iter_mut().enumerate() {
+            *token_settings = view.token_settings(i);
         }
-        let num_tokens = u64::from_le_bytes(account_data[8..16].try_into().unwrap_or_default());
-        let mut list: [TokenSettings; MAX_TOKENS_IN_ASSET_POOL] = [
-            TokenSettings {
-                token_mint: Pubkey::default(),
-                decimals: 0,
-                coingecko_id: [0; 30],
-                pda_token_account: Pubkey::default(),
-                oracle_type: 0,
-                oracle_account: Pubkey::default(),
-                oracle_index: 0,
-                oracle_confidence_pct: 0,
-                fixed_confidence_bps: 0,
-                token_swap_fee_after_tw_bps: 0,
-                token_swap_fee_before_tw_bps: 0,
-                is_live: 0,
-                lp_on: 0,
-                use_curve_data: 0,
-                additional_data: [0; 63],
-                oracle_price: OraclePrice { sell_price: 0, avg_price: 0, buy_price: 0, oracle_live: 0}
-            };
-            MAX_TOKENS_IN_ASSET_POOL
-        ];
-        for i in 0..num_tokens as usize {
-            let slice: [u8; 199] = account_data[16 + i*199..16 + (i+1)*199].try_into().unwrap();
-            list[i].token_mint = Pubkey::new_from_array(slice[0..32].try_into().unwrap_or_default());
-            list[i].decimals = slice[32];
-            list[i].pda_token_account = Pubkey::new_from_array(slice[63..95].try_into().unwrap_or_default());
-            list[i].oracle_type = slice[95];
-            list[i].oracle_account = Pubkey::new_from_array(slice[96..128].try_into().unwrap_or_default());
-            list[i].oracle_index = slice[128];
-            list[i].oracle_confidence_pct = slice[129];
-            list[i].fixed_confidence_bps = slice[130];
-            list[i].token_swap_fee_after_tw_bps = slice[131];
-            list[i].token_swap_fee_before_tw_bps = slice[132];
-            list[i].is_live = slice[133];
-            list[i].lp_on = slice[134];
-            list[i].use_curve_data = slice[135];
-            list[i].additional_data = slice[136..199].try_into().unwrap();
+        Ok(TokenList { num_tokens: view.num_tokens(), list, })
+    }
+
+    /// Serializes the first `num_tokens` tokens into a token list account, without oracle prices.
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut account_data = vec![0u8; TOKEN_LIST_ACCOUNT_SIZE];
+        account_data[0..8].copy_from_slice(&TOKEN_LIST_DISCRIMINATOR);
+        write_u64(&mut account_data, 8, self.num_tokens);
+        let num_tokens = (self.num_tokens as usize).min(MAX_TOKENS_IN_ASSET_POOL);
+        for (i, token_settings) in self.list.iter().take(num_tokens).enumerate() {
+            token_settings.write(&mut account_data[16 + i * 199..16 + (i + 1) * 199]);
+        }
+        account_data
+    }
+
+    /// The fee split is stored in the `additional_data` of the first token in the list.
+    pub fn fee_split(&self) -> FeeSplit {
+        FeeSplit::from_additional_data(&self.list[0].additional_data)
+    }
+
+    pub fn empty() -> TokenList {
+        TokenList { num_tokens: 0, list: [TokenList::empty_token_settings(); MAX_TOKENS_IN_ASSET_POOL] }
+    }
+
+    fn empty_token_settings() -> TokenSettings {
+        TokenSettings {
+            token_mint: Pubkey::default(),
+            decimals: 0,
+            coingecko_id: [0; 30],
+            pda_token_account: Pubkey::default(),
+            oracle_type: 0,
+            oracle_account: Pubkey::default(),
+            oracle_index: 0,
+            oracle_confidence_pct: 0,
+            fixed_confidence_bps: 0,
+            token_swap_fee_after_tw_bps: 0,
+            token_swap_fee_before_tw_bps: 0,
+            is_live: 0,
+            lp_on: 0,
+            use_curve_data: 0,
+            additional_data: [0; 63],
+            oracle_price: OraclePrice::empty(),
         }
-        Ok(TokenList { num_tokens, list, })
     }
 }
 
+/// Reads `TokenSettings` straight from the token list account bytes, one token at a time.
+#[derive(Clone, Copy)]
+pub struct TokenListView<'a> {
+    account_data: &'a [u8],
+}
+
+impl<'a> TokenListView<'a> {
+    pub fn new(account_data: &'a [u8]) -> Result<TokenListView<'a>> {
+        LayoutVersion::detect("TokenList", account_data, &TOKEN_LIST_LAYOUTS)?;
+        let view = TokenListView { account_data };
+        if view.num_tokens() > MAX_TOKENS_IN_ASSET_POOL as u64 {
+            return Err(SymmetryError::TooManyTokens {
+                account: "TokenList",
+                num_tokens: view.num_tokens(),
+                max: MAX_TOKENS_IN_ASSET_POOL,
+            }.into());
+        }
+        Ok(view)
+    }
+
+    /// Skips the size check, for bytes that already went through `new`. Reads never panic either way.
+    pub(crate) fn new_unchecked(account_data: &'a [u8]) -> TokenListView<'a> {
+        TokenListView { account_data }
+    }
+
+    pub fn num_tokens(&self) -> u64 {
+        read_u64(self.account_data, 8)
+    }
+
+    /// Settings of the token with id `token_id`, empty past `num_tokens`.
+    /// `oracle_price` is not part of the account and is left zeroed.
+    pub fn token_settings(&self, token_id: usize) -> TokenSettings {
+        let mut token_settings = TokenList::empty_token_settings();
+        let slice = match self.token_slice(token_id) {
+            Some(slice) => slice,
+            None => return token_settings,
+        };
+        token_settings.token_mint = read_pubkey(slice, 0);
+        token_settings.decimals = slice[32];
+        token_settings.coingecko_id = slice[33..63].try_into().unwrap_or_default();
+        token_settings.pda_token_account = read_pubkey(slice, 63);
+        token_settings.oracle_type = slice[95];
+        token_settings.oracle_account = read_pubkey(slice, 96);
+        token_settings.oracle_index = slice[128];
+        token_settings.oracle_confidence_pct = slice[129];
+        token_settings.fixed_confidence_bps = slice[130];
+        token_settings.token_swap_fee_after_tw_bps = slice[131];
+        token_settings.token_swap_fee_before_tw_bps = slice[132];
+        token_settings.is_live = slice[133];
+        token_settings.lp_on = slice[134];
+        token_settings.use_curve_data = slice[135];
+        token_settings.additional_data = slice[136..199].try_into().unwrap_or([0; 63]);
+        token_settings
+    }
+
+    pub fn token_mint(&self, token_id: usize) -> Pubkey {
+        self.token_slice(token_id).map(|slice| read_pubkey(slice, 0)).unwrap_or_default()
+    }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+    /// Id of the first token with `mint`, if it's in the list.
+    pub fn token_id(&self, mint: Pubkey) -> Option<usize> {
+        (0..MAX_TOKENS_IN_ASSET_POOL).find(|&token_id| self.token_mint(token_id) == mint)
+    }
+
+    pub fn fee_split(&self) -> FeeSplit {
+        FeeSplit::from_additional_data(&self.token_settings(0).additional_data)
+    }
+
+    fn token_slice(&self, token_id: usize) -> Option<&'a [u8]> {
+        if token_id >= MAX_TOKENS_IN_ASSET_POOL || token_id as u64 >= self.num_tokens() {
+            return None;
+        }
+        self.account_data.get(16 + token_id * 199..16 + (token_id + 1) * 199)
+    }
+}
+
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub struct TokenPriceData {
     pub amount: [u64; NUM_OF_POINTS_IN_CURVE_DATA],
     pub price: [u64; NUM_OF_POINTS_IN_CURVE_DATA],
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CurveData {
+    #[serde(with = "array")]
     pub buy: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL],
+    #[serde(with = "array")]
     pub sell: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL],
 }
 
 impl CurveData {
     #[inline]
     pub fn load<'a>(account_data: &[u8]) -> Result<CurveData> {
-        if account_data.len() != CURVE_DATA_ACCOUNT_SIZE {
-            return Err(Error::msg("Wrong account size for CurveData"));
-        }
-        let mut buy_vec: Vec<TokenPriceData> = Vec::new();
-        let mut sell_vec: Vec<TokenPriceData> = Vec::new();
-        for _ in 0..MAX_TOKENS_IN_ASSET_POOL {
-            buy_vec.push(TokenPriceData {
-                amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-                price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-            });
-            sell_vec.push(TokenPriceData {
-                amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-                price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-            });
+        let view = CurveDataView::new(account_data)?;
+        let mut curve_data = CurveData::empty();
+        for i in 0..MAX_TOKENS_IN_ASSET_POOL {
+            curve_data.buy[i] = view.buy(i);
+            curve_data.sell[i] = view.sell(i);
         }
-        let mut buy: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL] = buy_vec.try_into().unwrap();
-        let mut sell: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL] = sell_vec.try_into().unwrap();
+        Ok(curve_data)
+    }
+
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut account_data = vec![0u8; CURVE_DATA_ACCOUNT_SIZE];
+        account_data[0..8].copy_from_slice(&CURVE_DATA_DISCRIMINATOR);
         for i in 0..MAX_TOKENS_IN_ASSET_POOL {
             for j in 0..NUM_OF_POINTS_IN_CURVE_DATA {
-                buy[i].amount[j] = u64::from_le_bytes(account_data[(8 + i*160 + j*8)..(16 + i*160 + j*8)].try_into().unwrap_or_default());
-                buy[i].price[j] = u64::from_le_bytes(account_data[(88 + i*160 + j*8)..(96 + i*160 + j*8)].try_into().unwrap_or_default());
-                sell[i].amount[j] = u64::from_le_bytes(account_data[(32008 + i*160 + j*8)..(32016 + i*160 + j*8)].try_into().unwrap_or_default());
-                sell[i].price[j] = u64::from_le_bytes(account_data[(32088 + i*160 + j*8)..(32096 + i*160 + j*8)].try_into().unwrap_or_default());
+                write_u64(&mut account_data, 8 + i * 160 + j * 8, self.buy[i].amount[j]);
+                write_u64(&mut account_data, 88 + i * 160 + j * 8, self.buy[i].price[j]);
+                write_u64(&mut account_data, 32008 + i * 160 + j * 8, self.sell[i].amount[j]);
+                write_u64(&mut account_data, 32088 + i * 160 + j * 8, self.sell[i].price[j]);
             }
         }
-        Ok(CurveData {
-            buy,
-            sell,
-        })
+        account_data
     }
 
     pub fn empty() -> CurveData {
-        let mut buy_vec: Vec<TokenPriceData> = Vec::new();
-        let mut sell_vec: Vec<TokenPriceData> = Vec::new();
-        for _ in 0..MAX_TOKENS_IN_ASSET_POOL {
-            buy_vec.push(TokenPriceData {
-                amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-                price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-            });
-            sell_vec.push(TokenPriceData {
-                amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-                price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
-            });
-        }
-        let buy: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL] = buy_vec.try_into().unwrap();
-        let sell: [TokenPriceData; MAX_TOKENS_IN_ASSET_POOL] = sell_vec.try_into().unwrap();
+        let empty = TokenPriceData {
+            amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
+            price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
+        };
         CurveData {
-            buy,
-            sell,
+            buy: [empty; MAX_TOKENS_IN_ASSET_POOL],
+            sell: [empty; MAX_TOKENS_IN_ASSET_POOL],
         }
     }
 }
 
+/// Reads one token's buy or sell curve straight from the curve data account bytes.
 #[derive(Clone, Copy)]
+pub struct CurveDataView<'a> {
+    account_data: &'a [u8],
+}
+
+impl<'a> CurveDataView<'a> {
+    pub fn new(account_data: &'a [u8]) -> Result<CurveDataView<'a>> {
+        LayoutVersion::detect("CurveData", account_data, &CURVE_DATA_LAYOUTS)?;
+        Ok(CurveDataView { account_data })
+    }
+
+    /// Skips the size check, for bytes that already went through `new`. Reads never panic either way.
+    pub(crate) fn new_unchecked(account_data: &'a [u8]) -> CurveDataView<'a> {
+        CurveDataView { account_data }
+    }
+
+    pub fn buy(&self, token_id: usize) -> TokenPriceData {
+        self.token_price_data(8, token_id)
+    }
+
+    pub fn sell(&self, token_id: usize) -> TokenPriceData {
+        self.token_price_data(32008, token_id)
+    }
+
+    fn token_price_data(&self, offset: usize, token_id: usize) -> TokenPriceData {
+        let mut token_price_data = TokenPriceData {
+            amount: [0; NUM_OF_POINTS_IN_CURVE_DATA],
+            price: [0; NUM_OF_POINTS_IN_CURVE_DATA],
+        };
+        if token_id >= MAX_TOKENS_IN_ASSET_POOL {
+            return token_price_data;
+        }
+        let start = offset + token_id * 160;
+        for j in 0..NUM_OF_POINTS_IN_CURVE_DATA {
+            token_price_data.amount[j] = read_u64(self.account_data, start + j * 8);
+            token_price_data.price[j] = read_u64(self.account_data, start + 80 + j * 8);
+        }
+        token_price_data
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OraclePrice {
     pub sell_price: u64,
     pub avg_price: u64,
@@ -227,37 +506,50 @@ pub struct OraclePrice {
 }
 
 impl OraclePrice {
+    /// An offline price, for tokens whose oracle hasn't been loaded.
+    pub fn empty() -> OraclePrice {
+        OraclePrice { sell_price: 0, avg_price: 0, buy_price: 0, oracle_live: 0 }
+    }
+
     #[inline]
-    pub fn load<'a>(account_data: &[u8], token_settings: TokenSettings) -> Result<OraclePrice> {
-        if account_data.len() != ORACLE_ACCOUNT_SIZE[token_settings.oracle_type as usize] {
-            return Err(Error::msg("Wrong account size for oracle"));
+    /// Staleness is checked against `clock`, which off-chain has to come from the Clock sysvar.
+    /// `multi_feed_policy` decides if tokens priced by the multi-feed oracle can be live.
+    pub fn load<'a>(
+        account_data: &[u8],
+        token_settings: TokenSettings,
+        clock: &Clock,
+        multi_feed_policy: MultiFeedPolicy
+    ) -> Result<OraclePrice> {
+        if let Some(&expected) = ORACLE_ACCOUNT_SIZE.get(token_settings.oracle_type as usize) {
+            if account_data.len() != expected {
+                return Err(SymmetryError::BadAccountSize {
+                    account: "oracle",
+                    expected,
+                    got: account_data.len(),
+                }.into());
+            }
         }
         let (price, coinfidence, oracle_live) = match token_settings.oracle_type {
             0 => {
-                let valid_slot: u64 =  u64::from_le_bytes(account_data[40..48].try_into().unwrap_or_default());
-                let expo: i32 = i32::from_le_bytes(account_data[20..24].try_into().unwrap_or_default());
-                let price: i64 =  i64::from_le_bytes(account_data[208..216].try_into().unwrap_or_default());
-                let conf: u64 = u64::from_le_bytes(account_data[216..224].try_into().unwrap_or_default());
-                let status: u32 = u32::from_le_bytes(account_data[224..228].try_into().unwrap_or_default());
+                let price_account = PythPriceAccount::load(account_data)?;
                 let mut oracle_live = 1;
-        
-                if Clock::get().unwrap_or_default().slot >= 25 + valid_slot {
+
+                if clock.slot >= price_account.valid_slot.saturating_add(25) {
                     oracle_live = 0;
                 }
-                if status != 1 {
+                if price_account.status != PythPriceStatus::Trading {
                     oracle_live = 0;
                 }
-                if price < 0 {
+                if price_account.price < 0 {
                     oracle_live = 0;
                 }
-                if conf * 10 > price as u64 {
+                if price_account.conf.saturating_mul(10) > price_account.price.max(0) as u64 {
                     oracle_live = 0;
                 }
-                
-                let pow_num = u64::pow(10, (-expo) as u32);
-                let avg_price = mul_div(price as u64, ONE_USD, pow_num);
-                let confidence = mul_div(conf, ONE_USD, pow_num);
-    
+
+                let avg_price = price_account.price_usd()?;
+                let confidence = price_account.conf_usd()?;
+
                 let base_confidene = mul_div(
                     confidence, 
                     token_settings.oracle_confidence_pct as u64, 
@@ -267,27 +559,23 @@ impl OraclePrice {
                 (avg_price, base_confidene, oracle_live)
             },
             1 => {
+                let feed = MultiFeedOracleAccount::load(account_data)?.feed(token_settings.oracle_index)?;
+                let mantissa = feed.mantissa;
+                let write_timestamp = feed.write_timestamp;
+                let mut oracle_live: u8 = match multi_feed_policy {
+                    MultiFeedPolicy::Disabled => 0, // Disable lp for vaults containing switchboard tokens
+                    MultiFeedPolicy::TimeBasedConfidence => 1,
+                };
                 
-                let price_start = (token_settings.oracle_index as usize) * 8 + 9;
-                let price_end = price_start + 8;
-                let price: [u8; 8] = account_data[price_start..price_end].try_into().unwrap_or_default();
-                let mantissa: u64 = u64::from_le_bytes(price);
-    
-                let timestamp_start = price_start + 400;
-                let timetamp_end = price_end + 400;
-                let t: [u8; 8] = account_data[timestamp_start..timetamp_end].try_into().unwrap_or_default();
-                let write_timestamp: u64 = u64::from_le_bytes(t);
-                let mut oracle_live: u8 = 0; // Disable lp for vaults containing switchboard tokens
-                
-                let current_time = Clock::get().unwrap_or_default().unix_timestamp as u64;
-                if current_time > write_timestamp + 40 {
+                let current_time = clock.unix_timestamp.max(0) as u64;
+                if current_time > write_timestamp.saturating_add(40) {
                     oracle_live = 0;
                 }
             
                 let time_based_confidence_bps =
-                    if current_time > write_timestamp + 30
+                    if current_time > write_timestamp.saturating_add(30)
                         { 9900 } else
-                    if current_time > write_timestamp + 10
+                    if current_time > write_timestamp.saturating_add(10)
                         { token_settings.oracle_confidence_pct as u64 + (current_time - write_timestamp - 10) * 2 } else
                         { token_settings.oracle_confidence_pct as u64 };
             
@@ -306,7 +594,35 @@ impl OraclePrice {
                 
                 (avg_price, base_confidence, oracle_live)
             }
-            _ => (0, 0, 0)
+            2 => {
+                let price_update = PythPriceUpdateAccount::load(account_data)?;
+                let mut oracle_live = 1;
+
+                if clock.unix_timestamp > price_update.publish_time.saturating_add(PYTH_PULL_MAX_AGE_SECONDS) {
+                    oracle_live = 0;
+                }
+                if price_update.verification_level != PythVerificationLevel::Full {
+                    oracle_live = 0;
+                }
+                if price_update.price <= 0 {
+                    oracle_live = 0;
+                }
+                if price_update.conf.saturating_mul(10) > price_update.price.max(0) as u64 {
+                    oracle_live = 0;
+                }
+
+                let avg_price = price_update.price_usd()?;
+                let confidence = price_update.conf_usd()?;
+
+                let base_confidence = mul_div(
+                    confidence,
+                    token_settings.oracle_confidence_pct as u64,
+                    100
+                );
+
+                (avg_price, base_confidence, oracle_live)
+            }
+            oracle_type => return Err(SymmetryError::UnknownOracleType { oracle_type }.into()),
         };
     
         let additional_confidence = mul_div(
@@ -316,10 +632,316 @@ impl OraclePrice {
         );
     
         Ok(OraclePrice {
-            sell_price: price - coinfidence - additional_confidence,
+            sell_price: price.saturating_sub(coinfidence).saturating_sub(additional_confidence),
             avg_price: price,
-            buy_price: price + coinfidence + additional_confidence,
+            buy_price: price.saturating_add(coinfidence).saturating_add(additional_confidence),
             oracle_live: oracle_live,
         })
     }
 }
+
+/// Decodes the Clock sysvar account (bincode layout).
+pub fn load_clock(account_data: &[u8]) -> Result<Clock> {
+    if account_data.len() != CLOCK_ACCOUNT_SIZE {
+        return Err(SymmetryError::BadAccountSize {
+            account: "Clock",
+            expected: CLOCK_ACCOUNT_SIZE,
+            got: account_data.len(),
+        }.into());
+    }
+    Ok(Clock {
+        slot: u64::from_le_bytes(account_data[0..8].try_into().unwrap_or_default()),
+        epoch_start_timestamp: i64::from_le_bytes(account_data[8..16].try_into().unwrap_or_default()),
+        epoch: u64::from_le_bytes(account_data[16..24].try_into().unwrap_or_default()),
+        leader_schedule_epoch: u64::from_le_bytes(account_data[24..32].try_into().unwrap_or_default()),
+        unix_timestamp: i64::from_le_bytes(account_data[32..40].try_into().unwrap_or_default()),
+    })
+}
+
+#[cfg(test)]
+fn test_token_settings(oracle_type: u8) -> TokenSettings {
+    TokenSettings {
+        token_mint: Pubkey::new_unique(),
+        decimals: 9,
+        coingecko_id: [0; 30],
+        pda_token_account: Pubkey::new_unique(),
+        oracle_type,
+        oracle_account: Pubkey::new_unique(),
+        oracle_index: 0,
+        oracle_confidence_pct: 100,
+        fixed_confidence_bps: 0,
+        token_swap_fee_after_tw_bps: 0,
+        token_swap_fee_before_tw_bps: 0,
+        is_live: 1,
+        lp_on: 1,
+        use_curve_data: 0,
+        additional_data: [0; 63],
+        oracle_price: OraclePrice { sell_price: 0, avg_price: 0, buy_price: 0, oracle_live: 0 },
+    }
+}
+
+#[test]
+fn test_oracle_staleness_uses_given_clock() {
+    let token_settings = test_token_settings(0);
+    let mut pyth_account = vec![0u8; ORACLE_ACCOUNT_SIZE[0]];
+    pyth_account[0..4].copy_from_slice(&crate::amms::oracles::pyth::PYTH_MAGIC.to_le_bytes());
+    pyth_account[4..8].copy_from_slice(&crate::amms::oracles::pyth::PYTH_VERSION_2.to_le_bytes());
+    pyth_account[8..12].copy_from_slice(&crate::amms::oracles::pyth::PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+    pyth_account[20..24].copy_from_slice(&(-8i32).to_le_bytes());
+    pyth_account[40..48].copy_from_slice(&1_000u64.to_le_bytes());
+    pyth_account[208..216].copy_from_slice(&2_000_000_000i64.to_le_bytes());
+    pyth_account[216..224].copy_from_slice(&1_000_000u64.to_le_bytes());
+    pyth_account[224..228].copy_from_slice(&1u32.to_le_bytes());
+
+    let mut clock_account = vec![0u8; CLOCK_ACCOUNT_SIZE];
+    clock_account[0..8].copy_from_slice(&1_010u64.to_le_bytes());
+    let clock = load_clock(&clock_account).unwrap();
+    let price = OraclePrice::load(&pyth_account, token_settings, &clock, MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 1);
+    assert_eq!(price.avg_price, 20 * ONE_USD);
+    assert_eq!(price.buy_price - price.avg_price, ONE_USD / 100);
+
+    let stale_clock = Clock { slot: 1_025, ..Clock::default() };
+    let price = OraclePrice::load(&pyth_account, token_settings, &stale_clock, MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 0);
+}
+
+#[test]
+fn test_multi_feed_oracle_policy() {
+    use crate::amms::oracles::multi_feed::MULTI_FEED_ORACLE_ACCOUNT_SIZE;
+
+    let mut token_settings = test_token_settings(1);
+    token_settings.oracle_index = 3;
+    let mut oracle_account = vec![0u8; MULTI_FEED_ORACLE_ACCOUNT_SIZE];
+    oracle_account[9 + 3 * 8..17 + 3 * 8].copy_from_slice(&(50 * ONE_USD).to_le_bytes());
+    oracle_account[409 + 3 * 8..417 + 3 * 8].copy_from_slice(&1_000u64.to_le_bytes());
+    let clock_at = |unix_timestamp: i64| Clock { unix_timestamp, ..Clock::default() };
+
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_005), MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 0);
+
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_005), MultiFeedPolicy::TimeBasedConfidence).unwrap();
+    assert_eq!(price.oracle_live, 1);
+    assert_eq!(price.avg_price, 50 * ONE_USD * 99 / 100);
+    assert_eq!(price.buy_price - price.avg_price, price.avg_price / 100);
+
+    // Confidence widens after 10 seconds, the feed goes offline after 40
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_020), MultiFeedPolicy::TimeBasedConfidence).unwrap();
+    assert_eq!(price.buy_price - price.avg_price, price.avg_price * 120 / 10000);
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_041), MultiFeedPolicy::TimeBasedConfidence).unwrap();
+    assert_eq!(price.oracle_live, 0);
+    assert_eq!(price.sell_price, price.avg_price / 100);
+
+    token_settings.oracle_index = 50;
+    assert!(OraclePrice::load(&oracle_account, token_settings, &clock_at(1_005), MultiFeedPolicy::TimeBasedConfidence).is_err());
+}
+
+#[test]
+fn test_pyth_pull_oracle_price() {
+    use crate::amms::oracles::pyth_pull::price_update_v2_fixture;
+
+    let token_settings = test_token_settings(2);
+    let oracle_account = price_update_v2_fixture(PythVerificationLevel::Full, [1; 32], 2_000_000_000, 1_000_000, -8, 1_000);
+    let clock_at = |unix_timestamp: i64| Clock { unix_timestamp, ..Clock::default() };
+
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_010), MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 1);
+    assert_eq!(price.avg_price, 20 * ONE_USD);
+    assert_eq!(price.buy_price - price.avg_price, ONE_USD / 100);
+
+    let price = OraclePrice::load(&oracle_account, token_settings, &clock_at(1_011), MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 0);
+
+    let partially_verified = price_update_v2_fixture(
+        PythVerificationLevel::Partial { num_signatures: 5 }, [1; 32], 2_000_000_000, 1_000_000, -8, 1_000
+    );
+    let price = OraclePrice::load(&partially_verified, token_settings, &clock_at(1_000), MultiFeedPolicy::Disabled).unwrap();
+    assert_eq!(price.oracle_live, 0);
+    assert_eq!(price.avg_price, 20 * ONE_USD);
+
+    assert!(OraclePrice::load(&oracle_account[..133], token_settings, &clock_at(1_000), MultiFeedPolicy::Disabled).is_err());
+}
+
+#[test]
+fn test_token_list_coingecko_id_and_fee_split() {
+    let mut account_data = vec![0u8; TOKEN_LIST_ACCOUNT_SIZE];
+    account_data[0..8].copy_from_slice(&TOKEN_LIST_DISCRIMINATOR);
+    account_data[8..16].copy_from_slice(&2u64.to_le_bytes());
+    account_data[16 + 33..16 + 33 + 6].copy_from_slice(b"solana");
+    account_data[16 + 196..16 + 199].copy_from_slice(&[10, 20, 30]);
+    account_data[16 + 199 + 33..16 + 199 + 63].copy_from_slice(&[b'x'; 30]);
+
+    let token_list = TokenList::load(&account_data).unwrap();
+    assert_eq!(token_list.list[0].coingecko_id(), "solana");
+    assert_eq!(token_list.list[1].coingecko_id(), "x".repeat(30));
+    assert_eq!(token_list.list[2].coingecko_id(), "");
+    assert_eq!(token_list.fee_split(), FeeSplit { symmetry_pct: 10, host_pct: 20, manager_pct: 30 });
+}
+
+#[test]
+fn test_account_views_match_owned_decoding() {
+    let mut curve_account = vec![0u8; CURVE_DATA_ACCOUNT_SIZE];
+    curve_account[0..8].copy_from_slice(&CURVE_DATA_DISCRIMINATOR);
+    curve_account[8 + 7 * 160 + 8..8 + 7 * 160 + 16].copy_from_slice(&5u64.to_le_bytes());
+    curve_account[32008 + 99 * 160 + 152..32008 + 99 * 160 + 160].copy_from_slice(&9u64.to_le_bytes());
+    let curve_data = CurveData::load(&curve_account).unwrap();
+    let curve_view = CurveDataView::new(&curve_account).unwrap();
+    assert_eq!(curve_view.buy(7), curve_data.buy[7]);
+    assert_eq!(curve_view.buy(7).amount[1], 5);
+    assert_eq!(curve_view.sell(99).price[9], 9);
+    assert_eq!(curve_view.sell(100), curve_view.buy(0));
+
+    let mut token_list_account = vec![0u8; TOKEN_LIST_ACCOUNT_SIZE];
+    token_list_account[0..8].copy_from_slice(&TOKEN_LIST_DISCRIMINATOR);
+    let mint = Pubkey::new_unique();
+    token_list_account[8..16].copy_from_slice(&3u64.to_le_bytes());
+    token_list_account[16 + 2 * 199..16 + 2 * 199 + 32].copy_from_slice(&mint.to_bytes());
+    token_list_account[16 + 2 * 199 + 32] = 6;
+    let token_list_view = TokenListView::new(&token_list_account).unwrap();
+    assert_eq!(token_list_view.token_id(mint), Some(2));
+    assert_eq!(token_list_view.token_settings(2).decimals, 6);
+    assert_eq!(TokenList::load(&token_list_account).unwrap().list[2].token_mint, mint);
+
+    let mut fund_state_account = vec![0u8; FUND_STATE_ACCOUNT_SIZE];
+    fund_state_account[0..8].copy_from_slice(&FUND_STATE_DISCRIMINATOR);
+    fund_state_account[168..176].copy_from_slice(&2u64.to_le_bytes());
+    fund_state_account[336 + 8..344 + 8].copy_from_slice(&1_000u64.to_le_bytes());
+    let fund_state_view = FundStateView::new(&fund_state_account).unwrap();
+    assert_eq!(fund_state_view.num_of_tokens(), 2);
+    assert_eq!(fund_state_view.current_comp_amount(1), 1_000);
+    assert_eq!(fund_state_view.current_comp_amount(NUM_TOKENS_IN_FUND), 0);
+    assert!(FundStateView::new(&fund_state_account[1..]).is_err());
+}
+
+#[test]
+fn test_account_layout_validation() {
+    // Discriminators are not checked until they are confirmed on mainnet accounts
+    let mut fund_state_account = vec![0u8; FUND_STATE_ACCOUNT_SIZE];
+    assert!(FundState::load(&fund_state_account).is_ok());
+    // A token list of the same size is not a fund
+    assert!(TokenList::load(&fund_state_account).is_err());
+
+    fund_state_account.extend_from_slice(&[0; 64]);
+    let error = FundState::load(&fund_state_account).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<SymmetryError>(),
+        Some(&SymmetryError::UnsupportedLayout { account: "FundState", size: FUND_STATE_ACCOUNT_SIZE + 64 })
+    );
+}
+
+#[test]
+fn test_account_encoders_round_trip() {
+    let mut fund_state = FundState {
+        manager: Pubkey::new_unique(),
+        host_pubkey: Pubkey::new_unique(),
+        num_of_tokens: 2,
+        current_comp_token: [0; NUM_TOKENS_IN_FUND],
+        current_comp_amount: [0; NUM_TOKENS_IN_FUND],
+        target_weight: [0; NUM_TOKENS_IN_FUND],
+        weight_sum: 10000,
+        rebalance_threshold: 500,
+        lp_offset_threshold: 2,
+        lp_disabled: FUND_LP_DISABLED,
+    };
+    fund_state.current_comp_token[19] = 99;
+    fund_state.current_comp_amount[1] = 1_000;
+    fund_state.target_weight[0] = 6000;
+    let decoded = FundState::load(&fund_state.to_account_data()).unwrap();
+    assert_eq!(decoded.to_account_data(), fund_state.to_account_data());
+    assert_eq!(decoded.manager, fund_state.manager);
+    assert_eq!(decoded.current_comp_token[19], 99);
+    assert_eq!(decoded.lp_disabled, FUND_LP_DISABLED);
+
+    let mut token_list = TokenList::empty();
+    token_list.num_tokens = 2;
+    token_list.list[1] = test_token_settings(2);
+    token_list.list[1].coingecko_id[..4].copy_from_slice(b"usdc");
+    token_list.list[1].additional_data[62] = 30;
+    let account_data = token_list.to_account_data();
+    assert_eq!(account_data.len(), TOKEN_LIST_ACCOUNT_SIZE);
+    let decoded = TokenList::load(&account_data).unwrap();
+    assert_eq!(decoded.to_account_data(), account_data);
+    assert_eq!(decoded.list[1].token_mint, token_list.list[1].token_mint);
+    assert_eq!(decoded.list[1].coingecko_id(), "usdc");
+    assert_eq!(decoded.list[1].oracle_type, 2);
+
+    let mut curve_data = CurveData::empty();
+    curve_data.buy[3].amount[9] = 7;
+    curve_data.sell[99].price[0] = 11;
+    let decoded = CurveData::load(&curve_data.to_account_data()).unwrap();
+    assert_eq!(decoded.buy[3], curve_data.buy[3]);
+    assert_eq!(decoded.sell[99], curve_data.sell[99]);
+}
+
+#[test]
+fn test_decoded_state_json_round_trip() {
+    let mut token_list = TokenList::empty();
+    token_list.num_tokens = 1;
+    token_list.list[0] = test_token_settings(0);
+    token_list.list[0].additional_data[62] = 30;
+
+    let json = serde_json::to_value(token_list).unwrap();
+    assert_eq!(json["list"][0]["token_mint"], token_list.list[0].token_mint.to_string());
+    assert_eq!(json["list"].as_array().unwrap().len(), MAX_TOKENS_IN_ASSET_POOL);
+    let decoded: TokenList = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, token_list);
+
+    let mut curve_data = CurveData::empty();
+    curve_data.sell[42].price[3] = 5;
+    let decoded: CurveData = serde_json::from_str(&serde_json::to_string(&curve_data).unwrap()).unwrap();
+    assert_eq!(decoded, curve_data);
+
+    let mut json = serde_json::to_value(token_list.list[0]).unwrap();
+    json["oracle_account"] = "not a pubkey".into();
+    assert!(serde_json::from_value::<TokenSettings>(json).is_err());
+}
+
+#[test]
+fn test_decoders_reject_garbage_without_panicking() {
+    let mut seed: u64 = 0x9e3779b97f4a7c15;
+    let mut random_bytes = |len: usize| -> Vec<u8> {
+        (0..len).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        }).collect()
+    };
+    let clock = Clock { slot: u64::MAX, unix_timestamp: i64::MAX, ..Clock::default() };
+
+    for round in 0..200 {
+        for (size, discriminator) in [
+            (FUND_STATE_ACCOUNT_SIZE, FUND_STATE_DISCRIMINATOR),
+            (TOKEN_LIST_ACCOUNT_SIZE, TOKEN_LIST_DISCRIMINATOR),
+            (CURVE_DATA_ACCOUNT_SIZE, CURVE_DATA_DISCRIMINATOR),
+        ] {
+            let mut account_data = random_bytes(if round % 10 == 0 { round } else { size });
+            if account_data.len() >= 8 {
+                account_data[0..8].copy_from_slice(&discriminator);
+            }
+            let _ = FundState::load(&account_data);
+            let _ = TokenList::load(&account_data);
+            let _ = CurveData::load(&account_data);
+        }
+        for oracle_size in ORACLE_ACCOUNT_SIZE.iter().chain([0, 7, CLOCK_ACCOUNT_SIZE].iter()) {
+            let account_data = random_bytes(*oracle_size);
+            let mut token_settings = test_token_settings(round as u8 % 4);
+            token_settings.oracle_index = round as u8;
+            token_settings.oracle_confidence_pct = u8::MAX;
+            token_settings.fixed_confidence_bps = u8::MAX;
+            let _ = OraclePrice::load(&account_data, token_settings, &clock, MultiFeedPolicy::TimeBasedConfidence);
+            let _ = load_clock(&account_data);
+        }
+    }
+
+    let mut account_data = vec![0u8; TOKEN_LIST_ACCOUNT_SIZE];
+    account_data[0..8].copy_from_slice(&TOKEN_LIST_DISCRIMINATOR);
+    account_data[8..16].copy_from_slice(&101u64.to_le_bytes());
+    let error = TokenList::load(&account_data).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<SymmetryError>(),
+        Some(&SymmetryError::TooManyTokens { account: "TokenList", num_tokens: 101, max: MAX_TOKENS_IN_ASSET_POOL })
+    );
+    let error = OraclePrice::load(&[0; 16], test_token_settings(3), &clock, MultiFeedPolicy::Disabled).err().unwrap();
+    assert_eq!(error.downcast_ref::<SymmetryError>(), Some(&SymmetryError::UnknownOracleType { oracle_type: 3 }));
+}
@@ -0,0 +1,89 @@
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+/// Reasons a Symmetry account can't be decoded or a Symmetry swap can't be quoted.
+/// Returned wrapped in `anyhow::Error`, use `error.downcast_ref::<SymmetryError>()` to branch on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymmetryError {
+    /// The fund manager has disabled liquidity provision on the fund
+    LpDisabled,
+    /// The mint is not in the Symmetry token list
+    TokenNotSupported { mint: Pubkey },
+    /// The mint is supported but not part of the fund composition
+    TokenNotInFund { mint: Pubkey },
+    /// The token is not live in the Symmetry token list
+    TokenNotLive { mint: Pubkey },
+    /// Liquidity provision is turned off for the token
+    TokenLpDisabled { mint: Pubkey },
+    /// The oracle of one of the fund's tokens is offline
+    OracleOffline { token: Pubkey },
+    /// The swap would push the weight of the sold token above the allowed weight
+    WeightAboveMax { new: u64, allowed: u64 },
+    /// The swap would push the weight of the bought token below the allowed weight
+    WeightBelowMin { new: u64, allowed: u64 },
+    /// The fund holds less of the bought token than requested
+    NotEnoughLiquidity { requested: u64, available: u64 },
+    /// No input amount produces the requested exact-out amount
+    ExactOutUnreachable { out_amount: u64 },
+    /// Slippage tolerance above 10000 bps
+    InvalidSlippage { bps: u64 },
+    /// The account data length doesn't match the expected layout
+    BadAccountSize { account: &'static str, expected: usize, got: usize },
+    /// The oracle account has the right size but fails validation
+    InvalidOracleAccount { oracle: &'static str, reason: &'static str },
+    /// The account has a size no known layout version has
+    UnsupportedLayout { account: &'static str, size: usize },
+    /// The account is not owned by the Symmetry program
+    InvalidOwner { account: &'static str, owner: Pubkey },
+    /// The account holds more tokens than its layout has room for
+    TooManyTokens { account: &'static str, num_tokens: u64, max: usize },
+    /// The token is priced by an oracle type this crate can't decode
+    UnknownOracleType { oracle_type: u8 },
+    /// The input amount is too large to check against the fund's weight bounds
+    AmountTooLarge { amount: u64 },
+}
+
+impl fmt::Display for SymmetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymmetryError::LpDisabled =>
+                write!(f, "Manager has disabled liquidity provision on this fund"),
+            SymmetryError::TokenNotSupported { mint } =>
+                write!(f, "Token {} not found in supported tokens", mint),
+            SymmetryError::TokenNotInFund { mint } =>
+                write!(f, "Token {} not found in the fund composition", mint),
+            SymmetryError::TokenNotLive { mint } =>
+                write!(f, "Token {} is not live", mint),
+            SymmetryError::TokenLpDisabled { mint } =>
+                write!(f, "Liquidity provision is disabled for token {}", mint),
+            SymmetryError::OracleOffline { token } =>
+                write!(f, "Token {} has offline oracle status", token),
+            SymmetryError::WeightAboveMax { new, allowed } =>
+                write!(f, "From token weight {} exceeds max allowed weight {}", new, allowed),
+            SymmetryError::WeightBelowMin { new, allowed } =>
+                write!(f, "To token weight {} falls below min allowed weight {}", new, allowed),
+            SymmetryError::NotEnoughLiquidity { requested, available } =>
+                write!(f, "Not enough liquidity in the fund: requested {}, available {}", requested, available),
+            SymmetryError::ExactOutUnreachable { out_amount } =>
+                write!(f, "Unable to reach requested out amount {}", out_amount),
+            SymmetryError::InvalidSlippage { bps } =>
+                write!(f, "Slippage of {} bps exceeds 10000 bps", bps),
+            SymmetryError::BadAccountSize { account, expected, got } =>
+                write!(f, "Wrong account size for {}: expected {}, got {}", account, expected, got),
+            SymmetryError::InvalidOracleAccount { oracle, reason } =>
+                write!(f, "Invalid {} oracle account: {}", oracle, reason),
+            SymmetryError::UnsupportedLayout { account, size } =>
+                write!(f, "Unsupported {} layout of {} bytes, the program may have been upgraded", account, size),
+            SymmetryError::InvalidOwner { account, owner } =>
+                write!(f, "{} account is owned by {} instead of the Symmetry program", account, owner),
+            SymmetryError::TooManyTokens { account, num_tokens, max } =>
+                write!(f, "{} account has {} tokens, at most {} fit", account, num_tokens, max),
+            SymmetryError::UnknownOracleType { oracle_type } =>
+                write!(f, "Unknown oracle type {}", oracle_type),
+            SymmetryError::AmountTooLarge { amount } =>
+                write!(f, "Amount {} is too large to quote", amount),
+        }
+    }
+}
+
+impl std::error::Error for SymmetryError {}
@@ -0,0 +1,108 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use jupiter_amm_interface::KeyedAccount;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+
+use crate::amms::accounts::{FundStateView, FUND_LP_DISABLED, FUND_STATE_ACCOUNT_SIZE};
+use crate::amms::symmetry_market::SymmetryMarket;
+use crate::amms::symmetry_token_swap::SymmetryTokenSwap;
+
+/// Fund state accounts of every Symmetry fund open to liquidity provision, sorted by key.
+/// Accounts are filtered by size only, the discriminator is not confirmed on mainnet accounts yet.
+/// Accounts that don't decode as a fund state are skipped, whatever filters the RPC node applied.
+pub fn find_fund_state_accounts(client: &RpcClient) -> Result<Vec<KeyedAccount>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::DataSize(FUND_STATE_ACCOUNT_SIZE as u64)]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let mut fund_state_accounts: Vec<KeyedAccount> = client
+        .get_program_accounts_with_config(&SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS, config)?
+        .into_iter()
+        .filter(|(_, account)| account.owner == SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS)
+        .filter(|(_, account)| match FundStateView::new(&account.data) {
+            Ok(fund_state) => fund_state.lp_disabled() != FUND_LP_DISABLED,
+            Err(_) => false,
+        })
+        .map(|(key, account)| KeyedAccount { key, account, params: None })
+        .collect();
+    fund_state_accounts.sort_by_key(|fund_state_account| fund_state_account.key);
+    Ok(fund_state_accounts)
+}
+
+/// Every Symmetry fund open to liquidity provision, each with its own copy of the token list.
+/// Like funds from `SymmetryTokenSwap::from_keyed_account`, they quote after their first `update`.
+pub fn discover_funds(client: &RpcClient) -> Result<Vec<SymmetryTokenSwap>> {
+    let token_list_account = KeyedAccount {
+        key: SymmetryTokenSwap::TOKEN_LIST_ADDRESS,
+        account: client.get_account(&SymmetryTokenSwap::TOKEN_LIST_ADDRESS)?,
+        params: None,
+    };
+    find_fund_state_accounts(client)?
+        .iter()
+        .map(|fund_state_account| SymmetryTokenSwap::from_keyed_account(fund_state_account, &token_list_account))
+        .collect()
+}
+
+/// Every Symmetry fund open to liquidity provision, quoting against `market`.
+pub fn discover_market_funds(client: &RpcClient, market: &Arc<SymmetryMarket>) -> Result<Vec<SymmetryTokenSwap>> {
+    find_fund_state_accounts(client)?
+        .iter()
+        .map(|fund_state_account| market.fund(fund_state_account))
+        .collect()
+}
+
+#[test]
+fn test_discover_funds_with_mock_rpc() {
+    use crate::amms::accounts::FundState;
+    use crate::amms::symmetry_token_swap::test_fund_accounts;
+    use jupiter_amm_interface::Amm;
+    use serde_json::json;
+    use solana_account_decoder::UiAccount;
+    use solana_client::rpc_request::RpcRequest;
+    use solana_client::rpc_response::{Response, RpcKeyedAccount, RpcResponseContext};
+    use solana_sdk::{account::Account, pubkey::Pubkey};
+    use std::collections::HashMap;
+
+    let (key, account_map) = test_fund_accounts();
+    let fund_state_account = account_map[&key].clone();
+    let token_list_account = account_map[&SymmetryTokenSwap::TOKEN_LIST_ADDRESS].clone();
+    let mut lp_disabled_fund_state = FundState::load(&fund_state_account.data).unwrap();
+    lp_disabled_fund_state.lp_disabled = FUND_LP_DISABLED;
+    let not_a_fund_state = token_list_account.data.clone();
+    // Discriminators are not checked until confirmed on mainnet, a different one is still a fund
+    let other_key = Pubkey::new_unique();
+    let mut other_discriminator = fund_state_account.data.clone();
+    other_discriminator[0..8].copy_from_slice(&[0; 8]);
+
+    let ui_account = |account: &Account| UiAccount::encode(&Pubkey::default(), account, UiAccountEncoding::Base64, None, None);
+    let keyed_account = |key: Pubkey, data: Vec<u8>| RpcKeyedAccount {
+        pubkey: key.to_string(),
+        account: ui_account(&Account { data, ..fund_state_account.clone() }),
+    };
+    let mut mocks = HashMap::new();
+    mocks.insert(RpcRequest::GetProgramAccounts, json!([
+        keyed_account(Pubkey::new_unique(), lp_disabled_fund_state.to_account_data()),
+        keyed_account(key, fund_state_account.data.clone()),
+        keyed_account(Pubkey::new_unique(), not_a_fund_state),
+        keyed_account(other_key, other_discriminator),
+    ]));
+    mocks.insert(RpcRequest::GetAccountInfo, json!(Response {
+        context: RpcResponseContext { slot: 1, api_version: None },
+        value: Some(ui_account(&token_list_account)),
+    }));
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+    let funds = discover_funds(&client).unwrap();
+    let mut keys = vec![key, other_key];
+    keys.sort();
+    assert_eq!(funds.iter().map(|fund| fund.key()).collect::<Vec<Pubkey>>(), keys);
+    assert_eq!(funds[0].get_reserve_mints().len(), 2);
+}
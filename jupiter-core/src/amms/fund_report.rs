@@ -0,0 +1,85 @@
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+use crate::amms::error::SymmetryError;
+
+/// Composition of a fund as seen by the quoting code, for debugging failed quotes.
+/// Weights and drift are in bps, USD values in `ONE_USD` units. Drift is flagged with `!`
+/// when it exceeds the fund's `rebalance_threshold`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymmetryFundReport {
+    pub fund: Pubkey,
+    pub fund_worth: u64,
+    pub lp_disabled: bool,
+    pub rebalance_threshold: u64,
+    pub lp_offset_threshold: u64,
+    pub tokens: Vec<SymmetryFundReportRow>,
+}
+
+/// One token of the fund composition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymmetryFundReportRow {
+    pub token_mint: Pubkey,
+    pub decimals: u8,
+    pub amount: u64,
+    /// Value of `amount` at the oracle average price
+    pub usd_value: u64,
+    pub current_weight: u64,
+    /// `target_weight` scaled by the fund's `weight_sum`
+    pub target_weight: u64,
+    /// `current_weight - target_weight`
+    pub drift_bps: i64,
+    /// Highest weight a swap selling the token to the fund may leave, as enforced by quotes
+    pub allowed_from_target_weight: u64,
+    /// Lowest weight a swap buying the token from the fund may leave, as enforced by quotes
+    pub allowed_to_target_weight: u64,
+    pub oracle_live: bool,
+    /// `buy_price - sell_price` relative to `avg_price`
+    pub spread_bps: u64,
+    /// Why the token can't be swapped, `None` if it can
+    pub eligibility_error: Option<SymmetryError>,
+}
+
+fn usd(value: u64) -> Decimal {
+    Decimal::from_i128_with_scale(value as i128, 12).round_dp(2)
+}
+
+impl fmt::Display for SymmetryFundReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Fund {}  worth ${}  lp {}  rebalance threshold {} bps  lp offset threshold {}",
+            self.fund,
+            usd(self.fund_worth),
+            if self.lp_disabled { "disabled" } else { "enabled" },
+            self.rebalance_threshold,
+            self.lp_offset_threshold
+        )?;
+        writeln!(f, "mint                                         dec               amount        usd value  weight  target     min     max   drift  oracle  spread  status")?;
+        for row in &self.tokens {
+            let status = match &row.eligibility_error {
+                Some(error) => error.to_string(),
+                None => String::from("ok"),
+            };
+            let drift_flag = if row.drift_bps.unsigned_abs() > self.rebalance_threshold { "!" } else { "" };
+            writeln!(
+                f,
+                "{:<44} {:>3} {:>20} {:>16} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}  {}",
+                row.token_mint.to_string(),
+                row.decimals,
+                row.amount,
+                usd(row.usd_value).to_string(),
+                row.current_weight,
+                row.target_weight,
+                row.allowed_to_target_weight,
+                row.allowed_from_target_weight,
+                format!("{}{}", drift_flag, row.drift_bps),
+                if row.oracle_live { "live" } else { "offline" },
+                row.spread_bps,
+                status
+            )?;
+        }
+        Ok(())
+    }
+}
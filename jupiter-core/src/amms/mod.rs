@@ -1,7 +1,13 @@
 mod amm;
 mod spl_token_swap_amm;
-mod symmetry_token_swap;
-mod accounts;
+pub mod symmetry_token_swap;
+pub mod symmetry_market;
+pub mod fund_discovery;
+pub mod accounts;
+pub mod error;
+pub mod fund_report;
+pub mod oracles;
+mod serialization;
 
 #[cfg(test)]
 mod test_harness;
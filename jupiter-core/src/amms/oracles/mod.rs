@@ -0,0 +1,3 @@
+pub mod multi_feed;
+pub mod pyth;
+pub mod pyth_pull;
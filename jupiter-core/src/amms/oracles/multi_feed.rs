@@ -0,0 +1,84 @@
+use anyhow::Result;
+use std::convert::TryInto;
+
+use crate::amms::error::SymmetryError;
+
+pub const MULTI_FEED_ORACLE_ACCOUNT_SIZE: usize = 809;
+pub const MULTI_FEED_ORACLE_NUM_FEEDS: usize = 50;
+const PRICES_OFFSET: usize = 9;
+const WRITE_TIMESTAMPS_OFFSET: usize = PRICES_OFFSET + MULTI_FEED_ORACLE_NUM_FEEDS * 8;
+
+/// Whether tokens priced by the multi-feed (Switchboard) oracle can be quoted.
+/// The Symmetry program disables lp for funds holding such tokens, so quoting them is opt-in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiFeedPolicy {
+    /// Tokens priced by the multi-feed oracle are reported as offline
+    #[default]
+    Disabled,
+    /// Feeds written in the last 40 seconds are live, with confidence widening as they age
+    TimeBasedConfidence,
+}
+
+/// One price feed of the multi-feed oracle account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MultiFeedPrice {
+    pub mantissa: u64,
+    pub write_timestamp: u64,
+}
+
+/// The multi-feed oracle account: 50 price mantissas followed by their write timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiFeedOracleAccount {                             // 809 bytes
+    pub feeds: [MultiFeedPrice; MULTI_FEED_ORACLE_NUM_FEEDS],   // offset 9 / 409
+}
+
+impl MultiFeedOracleAccount {
+    pub fn load(account_data: &[u8]) -> Result<MultiFeedOracleAccount> {
+        if account_data.len() != MULTI_FEED_ORACLE_ACCOUNT_SIZE {
+            return Err(SymmetryError::BadAccountSize {
+                account: "multi-feed oracle",
+                expected: MULTI_FEED_ORACLE_ACCOUNT_SIZE,
+                got: account_data.len(),
+            }.into());
+        }
+        let mut feeds = [MultiFeedPrice::default(); MULTI_FEED_ORACLE_NUM_FEEDS];
+        for (i, feed) in feeds.iter_mut().enumerate() {
+            let price_start = PRICES_OFFSET + i * 8;
+            let timestamp_start = WRITE_TIMESTAMPS_OFFSET + i * 8;
+            feed.mantissa = u64::from_le_bytes(account_data[price_start..price_start + 8].try_into().unwrap_or_default());
+            feed.write_timestamp = u64::from_le_bytes(account_data[timestamp_start..timestamp_start + 8].try_into().unwrap_or_default());
+        }
+        Ok(MultiFeedOracleAccount { feeds })
+    }
+
+    /// Serializes the feeds into an oracle account, the header is left zeroed.
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut account_data = vec![0u8; MULTI_FEED_ORACLE_ACCOUNT_SIZE];
+        for (i, feed) in self.feeds.iter().enumerate() {
+            let price_start = PRICES_OFFSET + i * 8;
+            let timestamp_start = WRITE_TIMESTAMPS_OFFSET + i * 8;
+            account_data[price_start..price_start + 8].copy_from_slice(&feed.mantissa.to_le_bytes());
+            account_data[timestamp_start..timestamp_start + 8].copy_from_slice(&feed.write_timestamp.to_le_bytes());
+        }
+        account_data
+    }
+
+    pub fn feed(&self, oracle_index: u8) -> Result<MultiFeedPrice> {
+        self.feeds.get(oracle_index as usize).copied().ok_or_else(|| SymmetryError::InvalidOracleAccount {
+            oracle: "multi-feed",
+            reason: "oracle index out of range",
+        }.into())
+    }
+}
+
+#[test]
+fn test_multi_feed_oracle_round_trip() {
+    let mut oracle_account = MultiFeedOracleAccount { feeds: [MultiFeedPrice::default(); MULTI_FEED_ORACLE_NUM_FEEDS] };
+    oracle_account.feeds[0] = MultiFeedPrice { mantissa: 1, write_timestamp: 2 };
+    oracle_account.feeds[49] = MultiFeedPrice { mantissa: 3, write_timestamp: 4 };
+    let account_data = oracle_account.to_account_data();
+    assert_eq!(account_data.len(), MULTI_FEED_ORACLE_ACCOUNT_SIZE);
+    assert_eq!(MultiFeedOracleAccount::load(&account_data).unwrap(), oracle_account);
+    assert_eq!(oracle_account.feed(49).unwrap().write_timestamp, 4);
+    assert!(oracle_account.feed(50).is_err());
+}
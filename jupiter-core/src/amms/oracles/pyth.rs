@@ -0,0 +1,167 @@
+use anyhow::Result;
+use std::convert::TryInto;
+
+use crate::amms::accounts::ONE_USD;
+use crate::amms::error::SymmetryError;
+
+pub const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+pub const PYTH_VERSION_2: u32 = 2;
+pub const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+pub const PYTH_PRICE_ACCOUNT_SIZE: usize = 3312;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PythPriceStatus {
+    Unknown,
+    Trading,
+    Halted,
+    Auction,
+    Ignored,
+}
+
+impl PythPriceStatus {
+    fn from_u32(status: u32) -> PythPriceStatus {
+        match status {
+            1 => PythPriceStatus::Trading,
+            2 => PythPriceStatus::Halted,
+            3 => PythPriceStatus::Auction,
+            4 => PythPriceStatus::Ignored,
+            _ => PythPriceStatus::Unknown,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            PythPriceStatus::Unknown => 0,
+            PythPriceStatus::Trading => 1,
+            PythPriceStatus::Halted => 2,
+            PythPriceStatus::Auction => 3,
+            PythPriceStatus::Ignored => 4,
+        }
+    }
+}
+
+/// The fields of a Pyth v2 price account used for pricing Symmetry tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PythPriceAccount {                                   // 3312 bytes
+    pub expo: i32,                                              // offset 20
+    pub valid_slot: u64,                                        // offset 40
+    pub ema_price: i64,                                         // offset 48
+    pub ema_conf: u64,                                          // offset 72
+    pub timestamp: i64,                                         // offset 96
+    pub price: i64,                                             // offset 208
+    pub conf: u64,                                              // offset 216
+    pub status: PythPriceStatus,                                // offset 224
+    pub publish_slot: u64,                                      // offset 232
+}
+
+impl PythPriceAccount {
+    pub fn load(account_data: &[u8]) -> Result<PythPriceAccount> {
+        if account_data.len() != PYTH_PRICE_ACCOUNT_SIZE {
+            return Err(SymmetryError::BadAccountSize {
+                account: "Pyth price",
+                expected: PYTH_PRICE_ACCOUNT_SIZE,
+                got: account_data.len(),
+            }.into());
+        }
+        let read_u32 = |offset: usize| u32::from_le_bytes(account_data[offset..offset + 4].try_into().unwrap_or_default());
+        let read_u64 = |offset: usize| u64::from_le_bytes(account_data[offset..offset + 8].try_into().unwrap_or_default());
+        let read_i64 = |offset: usize| i64::from_le_bytes(account_data[offset..offset + 8].try_into().unwrap_or_default());
+
+        if read_u32(0) != PYTH_MAGIC {
+            return Err(SymmetryError::InvalidOracleAccount { oracle: "Pyth", reason: "wrong magic number" }.into());
+        }
+        if read_u32(4) != PYTH_VERSION_2 {
+            return Err(SymmetryError::InvalidOracleAccount { oracle: "Pyth", reason: "unsupported version" }.into());
+        }
+        if read_u32(8) != PYTH_ACCOUNT_TYPE_PRICE {
+            return Err(SymmetryError::InvalidOracleAccount { oracle: "Pyth", reason: "not a price account" }.into());
+        }
+
+        Ok(PythPriceAccount {
+            expo: read_u32(20) as i32,
+            valid_slot: read_u64(40),
+            ema_price: read_i64(48),
+            ema_conf: read_u64(72),
+            timestamp: read_i64(96),
+            price: read_i64(208),
+            conf: read_u64(216),
+            status: PythPriceStatus::from_u32(read_u32(224)),
+            publish_slot: read_u64(232),
+        })
+    }
+
+    /// Serializes the decoded fields into a price account, other fields are left zeroed.
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut account_data = vec![0u8; PYTH_PRICE_ACCOUNT_SIZE];
+        account_data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        account_data[4..8].copy_from_slice(&PYTH_VERSION_2.to_le_bytes());
+        account_data[8..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+        account_data[20..24].copy_from_slice(&self.expo.to_le_bytes());
+        account_data[40..48].copy_from_slice(&self.valid_slot.to_le_bytes());
+        account_data[48..56].copy_from_slice(&self.ema_price.to_le_bytes());
+        account_data[72..80].copy_from_slice(&self.ema_conf.to_le_bytes());
+        account_data[96..104].copy_from_slice(&self.timestamp.to_le_bytes());
+        account_data[208..216].copy_from_slice(&self.price.to_le_bytes());
+        account_data[216..224].copy_from_slice(&self.conf.to_le_bytes());
+        account_data[224..228].copy_from_slice(&self.status.to_u32().to_le_bytes());
+        account_data[232..240].copy_from_slice(&self.publish_slot.to_le_bytes());
+        account_data
+    }
+
+    /// Aggregate price in `ONE_USD` units, 0 for negative prices.
+    pub fn price_usd(&self) -> Result<u64> {
+        scale_to_usd(self.price.max(0) as u64, self.expo)
+    }
+
+    /// Aggregate confidence in `ONE_USD` units.
+    pub fn conf_usd(&self) -> Result<u64> {
+        scale_to_usd(self.conf, self.expo)
+    }
+}
+
+/// Converts `value * 10^expo` to `ONE_USD` units.
+pub fn scale_to_usd(value: u64, expo: i32) -> Result<u64> {
+    let out_of_range = || SymmetryError::InvalidOracleAccount { oracle: "Pyth", reason: "exponent out of range" };
+    let scale = 10u128.checked_pow(expo.unsigned_abs()).ok_or_else(out_of_range)?;
+    let scaled = if expo >= 0 {
+        (value as u128).checked_mul(ONE_USD as u128).and_then(|x| x.checked_mul(scale))
+    } else {
+        (value as u128).checked_mul(ONE_USD as u128).map(|x| x / scale)
+    };
+    Ok(scaled.and_then(|x| x.try_into().ok()).ok_or_else(out_of_range)?)
+}
+
+#[test]
+fn test_pyth_price_account_validation() {
+    let mut account_data = vec![0u8; PYTH_PRICE_ACCOUNT_SIZE];
+    account_data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+    account_data[4..8].copy_from_slice(&PYTH_VERSION_2.to_le_bytes());
+    account_data[8..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+    account_data[20..24].copy_from_slice(&(-6i32).to_le_bytes());
+    account_data[48..56].copy_from_slice(&1_490_000i64.to_le_bytes());
+    account_data[208..216].copy_from_slice(&1_500_000i64.to_le_bytes());
+    account_data[216..224].copy_from_slice(&1_000u64.to_le_bytes());
+    account_data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    account_data[232..240].copy_from_slice(&42u64.to_le_bytes());
+
+    let price_account = PythPriceAccount::load(&account_data).unwrap();
+    assert_eq!(price_account.status, PythPriceStatus::Trading);
+    assert_eq!(price_account.publish_slot, 42);
+    assert_eq!(price_account.ema_price, 1_490_000);
+    assert_eq!(price_account.price_usd().unwrap(), 3 * ONE_USD / 2);
+    assert_eq!(price_account.conf_usd().unwrap(), ONE_USD / 1000);
+    assert_eq!(PythPriceAccount::load(&price_account.to_account_data()).unwrap(), price_account);
+
+    // Positive exponents scale up instead of panicking
+    account_data[20..24].copy_from_slice(&2i32.to_le_bytes());
+    assert_eq!(PythPriceAccount::load(&account_data).unwrap().conf_usd().unwrap(), 100_000 * ONE_USD);
+    account_data[20..24].copy_from_slice(&40i32.to_le_bytes());
+    assert!(PythPriceAccount::load(&account_data).unwrap().price_usd().is_err());
+
+    account_data[0..4].copy_from_slice(&0u32.to_le_bytes());
+    let error = PythPriceAccount::load(&account_data).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<SymmetryError>(),
+        Some(&SymmetryError::InvalidOracleAccount { oracle: "Pyth", reason: "wrong magic number" })
+    );
+}
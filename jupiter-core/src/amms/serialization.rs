@@ -0,0 +1,32 @@
+//! Serde helpers for the decoded Symmetry accounts.
+
+/// Serializes a `Pubkey` as a base58 string.
+pub mod pubkey_base58 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let pubkey = String::deserialize(deserializer)?;
+        Pubkey::from_str(&pubkey).map_err(|_| D::Error::custom(format!("invalid base58 pubkey {}", pubkey)))
+    }
+}
+
+/// Serializes arrays of any length as sequences, serde only derives arrays of up to 32 elements.
+pub mod array {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(array.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>, const N: usize>(deserializer: D) -> Result<[T; N], D::Error> {
+        let elements = Vec::<T>::deserialize(deserializer)?;
+        let len = elements.len();
+        elements.try_into().map_err(|_| D::Error::invalid_length(len, &format!("an array of {} elements", N).as_str()))
+    }
+}
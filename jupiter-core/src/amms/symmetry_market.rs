@@ -0,0 +1,238 @@
+use anyhow::Result;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use solana_sdk::{pubkey::Pubkey, clock::Clock, sysvar::clock};
+use jupiter_amm_interface::{try_get_account_data, AccountMap, KeyedAccount};
+
+use crate::amms::accounts::{CurveDataView, TokenListView, OraclePrice, load_clock};
+use crate::amms::accounts::{CURVE_DATA_ACCOUNT_SIZE, TOKEN_LIST_ACCOUNT_SIZE, MAX_TOKENS_IN_ASSET_POOL};
+use crate::amms::oracles::multi_feed::MultiFeedPolicy;
+use crate::amms::symmetry_token_swap::SymmetryTokenSwap;
+
+/// The global token list, curve data and oracle prices, as of one `update`.
+/// Funds quote against a snapshot, so a market update never changes a quote halfway through.
+pub(crate) struct MarketSnapshot {
+    pub(crate) token_list: Vec<u8>,
+    pub(crate) curve_data: Vec<u8>,
+    /// Oracle prices indexed by token id
+    pub(crate) oracle_prices: Vec<OraclePrice>,
+}
+
+impl MarketSnapshot {
+    fn empty(token_list: Vec<u8>) -> MarketSnapshot {
+        MarketSnapshot {
+            token_list,
+            curve_data: vec![0; CURVE_DATA_ACCOUNT_SIZE],
+            oracle_prices: vec![OraclePrice::empty(); MAX_TOKENS_IN_ASSET_POOL],
+        }
+    }
+
+    /// Oracle accounts of `token_ids`, without duplicates.
+    pub(crate) fn oracle_accounts(&self, token_ids: impl Iterator<Item = usize>) -> Vec<Pubkey> {
+        let token_list = TokenListView::new_unchecked(&self.token_list);
+        let mut oracle_accounts: Vec<Pubkey> = Vec::new();
+        for token_id in token_ids {
+            let oracle_account = token_list.token_settings(token_id).oracle_account;
+            if oracle_account != Pubkey::default() && !oracle_accounts.contains(&oracle_account) {
+                oracle_accounts.push(oracle_account);
+            }
+        }
+        oracle_accounts
+    }
+}
+
+struct MarketState {
+    snapshot: Arc<MarketSnapshot>,
+    clock_override: Option<Clock>,
+    multi_feed_policy: MultiFeedPolicy,
+}
+
+/// The Symmetry state shared by all funds: token list, curve data and oracle prices.
+/// Funds created with `fund` only fetch their own fund state, so tracking many funds costs
+/// one market update per slot plus one account per fund.
+pub struct SymmetryMarket {
+    state: RwLock<MarketState>,
+}
+
+impl Default for SymmetryMarket {
+    fn default() -> Self {
+        SymmetryMarket::new()
+    }
+}
+
+impl SymmetryMarket {
+    pub fn new() -> SymmetryMarket {
+        SymmetryMarket::with_token_list(vec![0; TOKEN_LIST_ACCOUNT_SIZE])
+    }
+
+    pub(crate) fn with_token_list(token_list: Vec<u8>) -> SymmetryMarket {
+        SymmetryMarket {
+            state: RwLock::new(MarketState {
+                snapshot: Arc::new(MarketSnapshot::empty(token_list)),
+                clock_override: None,
+                multi_feed_policy: MultiFeedPolicy::default(),
+            }),
+        }
+    }
+
+    /// A fund quoting against this market. Its `update` only reads the fund state account,
+    /// the market has to be updated separately.
+    pub fn fund(self: &Arc<Self>, fund_state_account: &KeyedAccount) -> Result<SymmetryTokenSwap> {
+        SymmetryTokenSwap::from_market(fund_state_account, self.clone())
+    }
+
+    /// The token list, curve data, Clock sysvar and the oracles of every token in the list.
+    pub fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        let snapshot = self.snapshot();
+        let num_tokens = TokenListView::new_unchecked(&snapshot.token_list).num_tokens() as usize;
+        let mut accounts_to_update = vec![
+            SymmetryTokenSwap::CURVE_DATA_ADDRESS,
+            SymmetryTokenSwap::TOKEN_LIST_ADDRESS,
+            clock::ID,
+        ];
+        accounts_to_update.extend(snapshot.oracle_accounts(0..num_tokens));
+        accounts_to_update
+    }
+
+    /// Loads the accounts of `get_accounts_to_update` and prices every token in the list.
+    pub fn update(&self, account_map: &AccountMap) -> Result<()> {
+        let token_list = SymmetryTokenSwap::try_get_program_account_data(account_map, &SymmetryTokenSwap::TOKEN_LIST_ADDRESS, "TokenList")?;
+        let num_tokens = TokenListView::new(token_list)?.num_tokens() as usize;
+        self.update_tokens(account_map, 0..num_tokens)
+    }
+
+    /// Loads the token list, curve data and clock, and the oracle prices of `token_ids`.
+    /// Tokens whose oracle is missing from `account_map` or fails to decode are left unpriced, so only
+    /// the funds holding them fail to quote.
+    pub(crate) fn update_tokens(&self, account_map: &AccountMap, token_ids: impl Iterator<Item = usize>) -> Result<()> {
+        let curve_data = SymmetryTokenSwap::try_get_program_account_data(account_map, &SymmetryTokenSwap::CURVE_DATA_ADDRESS, "CurveData")?;
+        CurveDataView::new(curve_data)?;
+        let token_list_data = SymmetryTokenSwap::try_get_program_account_data(account_map, &SymmetryTokenSwap::TOKEN_LIST_ADDRESS, "TokenList")?;
+        let token_list = TokenListView::new(token_list_data)?;
+
+        let (clock_override, multi_feed_policy) = {
+            let state = self.read();
+            (state.clock_override.clone(), state.multi_feed_policy)
+        };
+        let clock = match clock_override {
+            Some(clock) => clock,
+            None => load_clock(try_get_account_data(account_map, &clock::ID)?)?,
+        };
+
+        let mut oracle_prices = vec![OraclePrice::empty(); MAX_TOKENS_IN_ASSET_POOL];
+        for token in token_ids {
+            let token_settings = token_list.token_settings(token);
+            let oracle_account = token_settings.oracle_account;
+            if oracle_account == Pubkey::default() || token >= MAX_TOKENS_IN_ASSET_POOL {
+                continue;
+            }
+            oracle_prices[token] = try_get_account_data(account_map, &oracle_account)
+                .and_then(|account_data| OraclePrice::load(account_data, token_settings, &clock, multi_feed_policy))
+                .unwrap_or_else(|_| OraclePrice::empty());
+        }
+
+        self.write().snapshot = Arc::new(MarketSnapshot {
+            token_list: token_list_data.to_vec(),
+            curve_data: curve_data.to_vec(),
+            oracle_prices,
+        });
+        Ok(())
+    }
+
+    /// Uses `clock` instead of the Clock sysvar for oracle staleness checks in `update`.
+    pub fn set_clock_override(&self, clock: Option<Clock>) {
+        self.write().clock_override = clock;
+    }
+
+    /// Allows quoting tokens priced by the multi-feed oracle, applied on the next `update`.
+    pub fn set_multi_feed_policy(&self, multi_feed_policy: MultiFeedPolicy) {
+        self.write().multi_feed_policy = multi_feed_policy;
+    }
+
+    pub(crate) fn snapshot(&self) -> Arc<MarketSnapshot> {
+        self.read().snapshot.clone()
+    }
+
+    /// An unshared market with the same state, for cloning funds that own their market.
+    pub(crate) fn duplicate(&self) -> SymmetryMarket {
+        let state = self.read();
+        SymmetryMarket {
+            state: RwLock::new(MarketState {
+                snapshot: state.snapshot.clone(),
+                clock_override: state.clock_override.clone(),
+                multi_feed_policy: state.multi_feed_policy,
+            }),
+        }
+    }
+
+    // Nothing panics while holding the lock, so a poisoned lock still holds consistent state
+    fn read(&self) -> RwLockReadGuard<'_, MarketState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MarketState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[test]
+fn test_funds_share_one_market_update() {
+    use crate::amms::accounts::TokenList;
+    use crate::amms::error::SymmetryError;
+    use crate::amms::symmetry_token_swap::test_fund_accounts;
+    use jupiter_amm_interface::{Amm, QuoteParams};
+
+    let (key, mut account_map) = test_fund_accounts();
+    // A third listed token, in no fund, whose oracle account was resized
+    let token_list_account = account_map.get_mut(&SymmetryTokenSwap::TOKEN_LIST_ADDRESS).unwrap();
+    let mut token_list = TokenList::load(&token_list_account.data).unwrap();
+    token_list.num_tokens = 3;
+    token_list.list[2] = token_list.list[1];
+    token_list.list[2].oracle_account = Pubkey::new_unique();
+    token_list_account.data = token_list.to_account_data();
+    let mut resized_oracle = account_map[&token_list.list[1].oracle_account].clone();
+    resized_oracle.data.push(0);
+    account_map.insert(token_list.list[2].oracle_account, resized_oracle);
+    let other_key = Pubkey::new_unique();
+    account_map.insert(other_key, account_map[&key].clone());
+
+    let market = Arc::new(SymmetryMarket::new());
+    market.set_clock_override(Some(Clock { slot: 1_000, ..Clock::default() }));
+    let mut funds: Vec<SymmetryTokenSwap> = [key, other_key].iter()
+        .map(|key| market.fund(&KeyedAccount { key: *key, account: account_map[key].clone(), params: None }).unwrap())
+        .collect();
+    assert_eq!(funds[1].get_accounts_to_update(), vec![other_key]);
+
+    // The first update only knows the zeroed token list, the second one requests every oracle
+    market.update(&account_map).unwrap();
+    let accounts_to_update = market.get_accounts_to_update();
+    assert_eq!(accounts_to_update.len(), 6);
+    market.update(&account_map).unwrap();
+
+    for fund in funds.iter_mut() {
+        fund.update(&account_map).unwrap();
+    }
+    let mints = funds[0].get_reserve_mints();
+    let quote_params = QuoteParams { in_amount: 1_000_000_000, input_mint: mints[0], output_mint: mints[1] };
+    let quotes: Vec<u64> = funds.iter().map(|fund| fund.quote(&quote_params).unwrap().out_amount).collect();
+    assert!(quotes[0] > 0);
+    assert_eq!(quotes[0], quotes[1]);
+
+    // A fund that owns its market quotes the same
+    let mut own_market = <SymmetryTokenSwap as Amm>::from_keyed_account(&KeyedAccount {
+        key,
+        account: account_map[&key].clone(),
+        params: None,
+    }).unwrap();
+    own_market.set_clock_override(Some(Clock { slot: 1_000, ..Clock::default() }));
+    own_market.update(&account_map).unwrap();
+    assert_eq!(own_market.quote(&quote_params).unwrap().out_amount, quotes[0]);
+
+    // A closed oracle only takes its own token offline, the funds holding it stop quoting
+    let missing_oracle = accounts_to_update[3];
+    account_map.remove(&missing_oracle);
+    market.update(&account_map).unwrap();
+    funds[0].update(&account_map).unwrap();
+    let error = funds[0].quote(&quote_params).unwrap_err();
+    assert!(matches!(error.downcast_ref::<SymmetryError>(), Some(SymmetryError::OracleOffline { .. })), "{}", error);
+}
@@ -1,7 +1,8 @@
 use anchor_lang::prelude::AccountMeta;
-use anyhow::{Result, Error};
+use anyhow::Result;
+use std::sync::Arc;
 
-use solana_sdk::{ pubkey, pubkey::Pubkey, instruction::Instruction};
+use solana_sdk::{ pubkey, pubkey::Pubkey, instruction::Instruction, clock::Clock, sysvar::clock};
 use rust_decimal::Decimal;
 
 use jupiter_amm_interface::Swap;
@@ -10,23 +11,86 @@ use jupiter_amm_interface::{
     SwapParams,
 };
 
-use crate::amms::accounts::{FundState, CurveData, TokenList, OraclePrice, TokenPriceData, TokenSettings};
-use crate::amms::accounts::{MAX_TOKENS_IN_ASSET_POOL, NUM_OF_POINTS_IN_CURVE_DATA, USE_CURVE_DATA, BPS_DIVIDER, LP_DISABLED, WEIGHT_MULTIPLIER, FUND_LP_DISABLED};
+use crate::amms::error::SymmetryError;
+use crate::amms::fund_report::{SymmetryFundReport, SymmetryFundReportRow};
+use crate::amms::symmetry_market::{MarketSnapshot, SymmetryMarket};
+use crate::amms::oracles::multi_feed::MultiFeedPolicy;
+use crate::amms::accounts::{FundStateView, CurveDataView, TokenListView, OraclePrice, TokenPriceData, TokenSettings};
+use crate::amms::accounts::{NUM_OF_POINTS_IN_CURVE_DATA, USE_CURVE_DATA, BPS_DIVIDER, LP_DISABLED, TOKEN_NOT_LIVE, WEIGHT_MULTIPLIER, FUND_LP_DISABLED};
 
 pub struct SymmetryTokenSwap {
     key: Pubkey,
     label: String,
-    fund_state: FundState,
-    token_list: TokenList,
-    curve_data: CurveData,
+    /// Raw account bytes, shared between clones and read through the account views
+    fund_state: Arc<[u8]>,
+    /// Token list, curve data and oracle prices, either owned by this fund or shared with others
+    market: Arc<SymmetryMarket>,
+    shares_market: bool,
+    market_snapshot: Arc<MarketSnapshot>,
     program_id: Pubkey,
 }
 
+/// How the `minimum_amount_out` of a swap instruction is derived.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slippage {
+    /// Tolerance in bps, applied to a fresh quote of the swap
+    Bps(u64),
+    /// Explicit minimum amount of the output token
+    MinimumAmountOut(u64),
+}
+
+/// All intermediate values of a Symmetry quote.
+/// Amounts and fees are in the output token, weights are scaled by `WEIGHT_MULTIPLIER`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SymmetryQuoteBreakdown {
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Output at oracle average prices, without spread or fees
+    pub fair_amount: u64,
+    /// Output at oracle sell/buy prices, capped by the fund's holdings
+    pub amount_without_fees: u64,
+    pub total_fees: u64,
+    /// Total fees relative to `fair_amount`, in hundredths of a bps
+    pub fee_bps: u64,
+    pub symmetry_fee: u64,
+    pub host_fee: u64,
+    pub manager_fee: u64,
+    pub fund_fee: u64,
+    pub from_new_weight: u64,
+    pub to_new_weight: u64,
+    pub allowed_from_target_weight: u64,
+    pub allowed_to_target_weight: u64,
+    pub from_target_weight: u64,
+    pub to_target_weight: u64,
+    /// Output if the whole `in_amount` traded at the zero-size price
+    pub marginal_out_amount: u64,
+    /// Shortfall of `out_amount` against `marginal_out_amount`, in percent
+    pub price_impact_pct: Decimal,
+}
+
+/// Quote of one ordered pair of a fund for a USD notional.
+#[derive(Debug)]
+pub struct SymmetryPairQuote {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    /// Out amount and fees, or the reason the pair can't be quoted
+    pub result: Result<SymmetryQuoteBreakdown>,
+}
+
+/// Quotes for every ordered pair of a fund's reserve mints.
+#[derive(Debug)]
+pub struct SymmetryQuoteMatrix {
+    pub mints: Vec<Pubkey>,
+    /// `quotes[i][j]` swaps `mints[i]` to `mints[j]`, `None` on the diagonal
+    pub quotes: Vec<Vec<Option<SymmetryPairQuote>>>,
+}
+
 impl SymmetryTokenSwap {
 
-    const SYMMETRY_PROGRAM_ADDRESS: Pubkey = pubkey!("2KehYt3KsEQR53jYcxjbQp2d2kCp4AkuQW68atufRwSr");
-    const TOKEN_LIST_ADDRESS: Pubkey = pubkey!("3SnUughtueoVrhevXTLMf586qvKNNXggNsc7NgoMUU1t");
-    const CURVE_DATA_ADDRESS: Pubkey = pubkey!("4QMjSHuM3iS7Fdfi8kZJfHRKoEJSDHEtEwqbChsTcUVK");
+    pub(crate) const SYMMETRY_PROGRAM_ADDRESS: Pubkey = pubkey!("2KehYt3KsEQR53jYcxjbQp2d2kCp4AkuQW68atufRwSr");
+    pub(crate) const TOKEN_LIST_ADDRESS: Pubkey = pubkey!("3SnUughtueoVrhevXTLMf586qvKNNXggNsc7NgoMUU1t");
+    pub(crate) const CURVE_DATA_ADDRESS: Pubkey = pubkey!("4QMjSHuM3iS7Fdfi8kZJfHRKoEJSDHEtEwqbChsTcUVK");
     const PDA_ADDRESS: Pubkey = pubkey!("BLBYiq48WcLQ5SxiftyKmPtmsZPUBEnDEjqEnKGAR4zx");
     const SWAP_FEE_ADDRESS: Pubkey = pubkey!("AWfpfzA6FYbqx4JLz75PDgsjH7jtBnnmJ6MXW5zNY2Ei");
 
@@ -35,52 +99,101 @@ impl SymmetryTokenSwap {
 
     const SYMMETRY_PROGRAM_SWAP_INSTRUCTION_ID: u64 = 219478785678209410;
 
+    /// Largest `in_amount` the 1% safety margin of `quote_detailed` can be applied to
+    const MAX_QUOTABLE_AMOUNT: u64 = u64::MAX / 101;
+
     pub fn from_keyed_account(fund_state_account: &KeyedAccount, token_list_account: &KeyedAccount) -> Result<Self> {
-        let fund_state_loader = FundState::load(&fund_state_account.account.data);
-        if let Err(e) = fund_state_loader {
-            return Err(e);
-        }
-        let fund_state = fund_state_loader.unwrap();
-        let token_list_loader = TokenList::load(&token_list_account.account.data);
-        if let Err(e) = token_list_loader {
-            return Err(e);
-        }
-        let token_list = token_list_loader.unwrap();
+        SymmetryTokenSwap::check_owner("FundState", &fund_state_account.account.owner)?;
+        SymmetryTokenSwap::check_owner("TokenList", &token_list_account.account.owner)?;
+        FundStateView::new(&fund_state_account.account.data)?;
+        TokenListView::new(&token_list_account.account.data)?;
+
+        Ok(SymmetryTokenSwap::new(
+            fund_state_account.key,
+            fund_state_account.account.data.as_slice().into(),
+            Arc::new(SymmetryMarket::with_token_list(token_list_account.account.data.clone())),
+            false
+        ))
+    }
+
+    /// A fund quoting against a shared market, see `SymmetryMarket::fund`.
+    pub(crate) fn from_market(fund_state_account: &KeyedAccount, market: Arc<SymmetryMarket>) -> Result<Self> {
+        SymmetryTokenSwap::check_owner("FundState", &fund_state_account.account.owner)?;
+        FundStateView::new(&fund_state_account.account.data)?;
 
-        Ok(Self {
-            key: fund_state_account.key,
+        Ok(SymmetryTokenSwap::new(
+            fund_state_account.key,
+            fund_state_account.account.data.as_slice().into(),
+            market,
+            true
+        ))
+    }
+
+    fn new(key: Pubkey, fund_state: Arc<[u8]>, market: Arc<SymmetryMarket>, shares_market: bool) -> Self {
+        Self {
+            key,
             label: String::from("Symmetry"),
-            fund_state: fund_state,
-            token_list: token_list,
-            curve_data: CurveData::empty(),
+            fund_state,
+            market_snapshot: market.snapshot(),
+            market,
+            shares_market,
             program_id: SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS,
-        })
+        }
+    }
+
+    /// Uses `clock` instead of the Clock sysvar for oracle staleness checks in `update`.
+    /// Funds of a shared `SymmetryMarket` set it for the whole market.
+    pub fn set_clock_override(&mut self, clock: Option<Clock>) {
+        self.market.set_clock_override(clock);
+    }
+
+    /// Allows quoting funds holding tokens priced by the multi-feed oracle, applied on the next `update`.
+    /// Funds of a shared `SymmetryMarket` set it for the whole market.
+    pub fn set_multi_feed_policy(&mut self, multi_feed_policy: MultiFeedPolicy) {
+        self.market.set_multi_feed_policy(multi_feed_policy);
+    }
+
+    pub(crate) fn check_owner(account: &'static str, owner: &Pubkey) -> Result<()> {
+        if *owner != SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS {
+            return Err(SymmetryError::InvalidOwner { account, owner: *owner }.into())
+        }
+        Ok(())
+    }
+
+    /// Same as `try_get_account_data`, for accounts that have to be owned by the Symmetry program.
+    pub(crate) fn try_get_program_account_data<'a>(account_map: &'a AccountMap, address: &Pubkey, account: &'static str) -> Result<&'a [u8]> {
+        let account_data = try_get_account_data(account_map, address)?;
+        SymmetryTokenSwap::check_owner(account, &account_map[address].owner)?;
+        Ok(account_data)
+    }
+
+    fn fund_state(&self) -> FundStateView<'_> {
+        FundStateView::new_unchecked(&self.fund_state)
+    }
+
+    fn token_list(&self) -> TokenListView<'_> {
+        TokenListView::new_unchecked(&self.market_snapshot.token_list)
+    }
+
+    fn curve_data(&self) -> CurveDataView<'_> {
+        CurveDataView::new_unchecked(&self.market_snapshot.curve_data)
+    }
+
+    /// Settings of the token with id `token_id`, with its last loaded oracle price.
+    fn token_settings(&self, token_id: usize) -> TokenSettings {
+        let mut token_settings = self.token_list().token_settings(token_id);
+        token_settings.oracle_price = self.market_snapshot.oracle_prices.get(token_id).copied().unwrap_or(OraclePrice::empty());
+        token_settings
     }
 
     fn clone(&self) -> SymmetryTokenSwap {
         SymmetryTokenSwap {
             key: self.key,
             label: self.label.clone(),
-            fund_state: FundState {
-                manager: self.fund_state.manager,
-                host_pubkey: self.fund_state.host_pubkey,
-                num_of_tokens: self.fund_state.num_of_tokens,
-                current_comp_token: self.fund_state.current_comp_token,
-                current_comp_amount: self.fund_state.current_comp_amount,
-                target_weight: self.fund_state.target_weight,
-                weight_sum: self.fund_state.weight_sum,
-                rebalance_threshold: self.fund_state.rebalance_threshold,
-                lp_offset_threshold: self.fund_state.lp_offset_threshold,
-                lp_disabled: self.fund_state.lp_disabled,
-            },
-            token_list: TokenList {
-                num_tokens: self.token_list.num_tokens,
-                list: self.token_list.list
-            },
-            curve_data: CurveData {
-                buy: self.curve_data.buy,
-                sell: self.curve_data.sell
-            },
+            fund_state: self.fund_state.clone(),
+            market: if self.shares_market { self.market.clone() } else { Arc::new(self.market.duplicate()) },
+            shares_market: self.shares_market,
+            market_snapshot: self.market_snapshot.clone(),
             program_id: self.program_id,
         }
     }
@@ -93,6 +206,12 @@ impl SymmetryTokenSwap {
         }
     }
 
+    pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Option<u64> {
+        if c == 0 { return None; }
+        let product = (a as u128).checked_mul(b as u128)?;
+        product.checked_add(c as u128 - 1)?.checked_div(c as u128)?.try_into().ok()
+    }
+
     pub fn amount_to_usd_value(amount: u64, decimals: u8, price: u64) -> u64 {
         SymmetryTokenSwap::mul_div(amount, price, u64::pow(10,decimals as u32))
     }
@@ -216,169 +335,282 @@ impl SymmetryTokenSwap {
         current_output_amount
     }
 
-    
-}
+    /// Inverse of `compute_value_of_sold_token`: the amount of the sold token needed to
+    /// receive at least `value` (after fees). Returns `None` if the value can't be reached.
+    pub fn compute_amount_of_sold_token(
+        value: u64,
+        token_settings: TokenSettings,
+        price: OraclePrice,
+        start_amount: u64,
+        target_amount: u64,
+        curve_data: TokenPriceData
+    ) -> Option<u64> {
+        let mut current_amount = start_amount;
+        let mut curve_offset = start_amount.saturating_sub(target_amount);
+        let mut current_input_amount: u64 = 0;
+        let mut value_left: u64 = value;
+        let mut current_price = price.sell_price;
 
-impl Amm for SymmetryTokenSwap {
+        if value == 0 { return Some(0); }
 
-    fn from_keyed_account(keyed_account: &KeyedAccount) -> Result<Self> {
-        SymmetryTokenSwap::from_keyed_account(keyed_account, keyed_account)
-    }
+        for step in 0..NUM_OF_POINTS_IN_CURVE_DATA+1 {
+            // The last interval is unbounded, same as in the forward computation
+            let step_amount = if step < NUM_OF_POINTS_IN_CURVE_DATA
+                { Some(curve_data.amount[step]) } else { None };
+            if step < NUM_OF_POINTS_IN_CURVE_DATA && curve_data.price[step] < current_price
+                && token_settings.use_curve_data == USE_CURVE_DATA {
+                current_price = curve_data.price[step];
+            }
+            if step == NUM_OF_POINTS_IN_CURVE_DATA { curve_offset = 0; }
+            let amount_in_interval = match step_amount {
+                Some(step_amount) if step_amount <= curve_offset => {
+                    curve_offset -= step_amount;
+                    continue;
+                }
+                Some(step_amount) => Some(step_amount - curve_offset),
+                None => None,
+            };
+            curve_offset = 0;
 
-    // fn from_keyed_account(keyed_account_1: &KeyedAccount, keyed_account_2: &KeyedAccount) -> Result<Self> {
-    //     SymmetryTokenSwap::from_keyed_account(keyed_account_1, keyed_account_2)
-    // }
+            let amount_to_tw = target_amount.saturating_sub(current_amount);
+            let (amount_before_tw, amount_after_tw) = match amount_in_interval {
+                Some(amount) if amount <= amount_to_tw => (amount, Some(0)),
+                Some(amount) => (amount_to_tw, Some(amount - amount_to_tw)),
+                None => (amount_to_tw, None),
+            };
+            let portions = [
+                (Some(amount_before_tw), token_settings.token_swap_fee_before_tw_bps),
+                (amount_after_tw, token_settings.token_swap_fee_after_tw_bps),
+            ];
 
-    fn label(&self) -> String {
-        self.label.clone()
-    }
+            for (portion, fee_bps) in portions {
+                if portion == Some(0) { continue; }
+                let fee_bps = fee_bps as u64;
+                if let Some(portion) = portion {
+                    let portion_value = SymmetryTokenSwap::amount_to_usd_value(portion, token_settings.decimals, current_price);
+                    let portion_net_value = portion_value - SymmetryTokenSwap::mul_div(portion_value, fee_bps, BPS_DIVIDER);
+                    if portion_net_value < value_left {
+                        value_left -= portion_net_value;
+                        current_input_amount = current_input_amount.checked_add(portion)?;
+                        current_amount = current_amount.saturating_add(portion);
+                        continue;
+                    }
+                }
+                if current_price == 0 { return None; }
+                let gross_value = SymmetryTokenSwap::mul_div_ceil(value_left, BPS_DIVIDER, BPS_DIVIDER - fee_bps)?;
+                let mut amount = SymmetryTokenSwap::mul_div_ceil(gross_value, u64::pow(10, token_settings.decimals as u32), current_price)?;
+                if let Some(portion) = portion {
+                    amount = amount.min(portion);
+                }
+                return current_input_amount.checked_add(amount);
+            }
+        };
 
-    fn program_id(&self) -> Pubkey {
-        self.program_id
+        None
     }
 
-    fn key(&self) -> Pubkey {
-        self.key
-    }
+    /// Inverse of `compute_amount_of_bought_token`: the value that has to be spent to
+    /// receive at least `amount` of the bought token. Returns `None` if it can't be reached.
+    pub fn compute_value_of_bought_token(
+        amount: u64,
+        token_settings: TokenSettings,
+        price: OraclePrice,
+        start_amount: u64,
+        target_amount: u64,
+        curve_data: TokenPriceData,
+    ) -> Option<u64> {
+        let mut current_amount = start_amount;
+        let mut curve_offset = target_amount.saturating_sub(start_amount);
+        let mut current_input_value: u64 = 0;
+        let mut amount_left: u64 = amount;
+        let mut current_price = price.buy_price;
 
-    fn get_reserve_mints(&self) -> Vec<Pubkey> {
-        let mut vec: Vec<Pubkey> = Vec::new();
-        for i in 0..self.fund_state.num_of_tokens as usize {
-            if self.token_list.list[self.fund_state.current_comp_token[i] as usize].lp_on != LP_DISABLED {
-                vec.push(self.token_list.list[self.fund_state.current_comp_token[i] as usize].token_mint)
+        if amount == 0 { return Some(0); }
+
+        for step in 0..NUM_OF_POINTS_IN_CURVE_DATA+1 {
+            let step_amount = if step < NUM_OF_POINTS_IN_CURVE_DATA
+                { Some(curve_data.amount[step]) } else { None };
+            if step < NUM_OF_POINTS_IN_CURVE_DATA && curve_data.price[step] > current_price
+                && token_settings.use_curve_data == USE_CURVE_DATA {
+                current_price = curve_data.price[step];
             }
-        }
-        return vec;
-    }
+            if step == NUM_OF_POINTS_IN_CURVE_DATA { curve_offset = 0; }
+            let amount_in_interval = match step_amount {
+                Some(step_amount) if step_amount <= curve_offset => {
+                    curve_offset -= step_amount;
+                    continue;
+                }
+                Some(step_amount) => Some(step_amount - curve_offset),
+                None => None,
+            };
+            curve_offset = 0;
 
-    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        let mut accounts_to_update: Vec<Pubkey> = Vec::new();
-        accounts_to_update.push(SymmetryTokenSwap::CURVE_DATA_ADDRESS);
-        accounts_to_update.push(self.key);
-        for i in 0..MAX_TOKENS_IN_ASSET_POOL {
-            if self.token_list.list[i].oracle_account != Pubkey::default() {
-                accounts_to_update.push(self.token_list.list[i].oracle_account)
+            let amount_to_tw = current_amount.saturating_sub(target_amount);
+            let (amount_before_tw, amount_after_tw) = match amount_in_interval {
+                Some(amount) if amount <= amount_to_tw => (amount, Some(0)),
+                Some(amount) => (amount_to_tw, Some(amount - amount_to_tw)),
+                None => (amount_to_tw, None),
+            };
+            let portions = [
+                (Some(amount_before_tw), token_settings.token_swap_fee_before_tw_bps),
+                (amount_after_tw, token_settings.token_swap_fee_after_tw_bps),
+            ];
+
+            for (portion, fee_bps) in portions {
+                if portion == Some(0) { continue; }
+                let fee_bps = fee_bps as u64;
+                if let Some(portion) = portion {
+                    let portion_net_amount = portion - SymmetryTokenSwap::mul_div(portion, fee_bps, BPS_DIVIDER);
+                    if portion_net_amount < amount_left {
+                        amount_left -= portion_net_amount;
+                        current_input_value = current_input_value.checked_add(
+                            SymmetryTokenSwap::amount_to_usd_value(portion, token_settings.decimals, current_price)
+                        )?;
+                        current_amount = current_amount.saturating_sub(portion_net_amount);
+                        continue;
+                    }
+                }
+                let gross_amount = SymmetryTokenSwap::mul_div_ceil(amount_left, BPS_DIVIDER, BPS_DIVIDER - fee_bps)?;
+                let value = SymmetryTokenSwap::mul_div_ceil(gross_amount, current_price, u64::pow(10, token_settings.decimals as u32))?;
+                return current_input_value.checked_add(value);
             }
-        }
-        return accounts_to_update;
+        };
+
+        None
     }
 
-    fn update(&mut self, account_map: &AccountMap) -> Result<()> {
-        let curve_data_loader = CurveData::load(try_get_account_data(account_map, &SymmetryTokenSwap::CURVE_DATA_ADDRESS)?);
-        if let Err(e) = curve_data_loader {
-            return Err(e);
+    /// Quotes the smallest amount of `input_mint` that receives at least `out_amount` of `output_mint`.
+    /// The returned quote is validated against the same weight bounds as an exact-in quote.
+    pub fn quote_exact_out(&self, input_mint: Pubkey, output_mint: Pubkey, out_amount: u64) -> Result<Quote> {
+        let context = self.pair_context(input_mint, output_mint)?;
+        let fund_state = self.fund_state();
+
+        if out_amount > fund_state.current_comp_amount(context.to_token_index) {
+            return Err(SymmetryError::NotEnoughLiquidity {
+                requested: out_amount,
+                available: fund_state.current_comp_amount(context.to_token_index),
+            }.into())
         }
-        self.curve_data = curve_data_loader.unwrap();
 
-        let fund_state_loader = FundState::load(try_get_account_data(account_map, &self.key)?);
-        if let Err(e) = fund_state_loader {
-            return Err(e);
+        let value = SymmetryTokenSwap::compute_value_of_bought_token(
+            out_amount,
+            context.to_token_settings,
+            context.to_token_settings.oracle_price,
+            fund_state.current_comp_amount(context.to_token_index),
+            context.to_token_target_amount,
+            self.curve_data().buy(context.to_token_id as usize),
+        ).ok_or(SymmetryError::ExactOutUnreachable { out_amount })?;
+
+        let estimated_in_amount = SymmetryTokenSwap::compute_amount_of_sold_token(
+            value,
+            context.from_token_settings,
+            context.from_token_settings.oracle_price,
+            fund_state.current_comp_amount(context.from_token_index),
+            context.from_token_target_amount,
+            self.curve_data().sell(context.from_token_id as usize),
+        ).ok_or(SymmetryError::ExactOutUnreachable { out_amount })?;
+
+        let quote_for = |in_amount: u64| self.quote(&QuoteParams {
+            in_amount,
+            input_mint,
+            output_mint,
+        });
+
+        if out_amount == 0 {
+            return quote_for(0);
         }
-        self.fund_state = fund_state_loader.unwrap();
-
-        for i in 0..MAX_TOKENS_IN_ASSET_POOL {
-            if self.token_list.list[i].oracle_account != Pubkey::default() {
-                let oracle_loader = OraclePrice::load(
-                    try_get_account_data(account_map, &self.token_list.list[i].oracle_account)?,
-                    self.token_list.list[i]
-                );
-                if let Err(e) = oracle_loader {
-                    return Err(e);
+
+        // Rounding in the forward computation can leave the estimate slightly short or over,
+        // so bracket the smallest enough in_amount around it and binary search the bracket.
+        // `low` is always short of `out_amount`, `high` always enough.
+        let estimated_in_amount = estimated_in_amount.clamp(1, SymmetryTokenSwap::MAX_QUOTABLE_AMOUNT);
+        let mut step = (estimated_in_amount / 1000).max(1);
+        let estimated_quote = quote_for(estimated_in_amount)?;
+        let (mut low, mut high, mut high_quote) = if estimated_quote.out_amount >= out_amount {
+            let (mut high, mut high_quote) = (estimated_in_amount, estimated_quote);
+            loop {
+                let low = high.saturating_sub(step);
+                if low == 0 {
+                    break (0, high, high_quote);
+                }
+                let low_quote = quote_for(low)?;
+                if low_quote.out_amount < out_amount {
+                    break (low, high, high_quote);
+                }
+                (high, high_quote) = (low, low_quote);
+                step = step.saturating_mul(2);
+            }
+        } else {
+            let mut low = estimated_in_amount;
+            loop {
+                if low == SymmetryTokenSwap::MAX_QUOTABLE_AMOUNT {
+                    return Err(SymmetryError::ExactOutUnreachable { out_amount }.into());
                 }
-                self.token_list.list[i].oracle_price = oracle_loader.unwrap();
+                let high = low.saturating_add(step).min(SymmetryTokenSwap::MAX_QUOTABLE_AMOUNT);
+                let high_quote = quote_for(high)?;
+                if high_quote.out_amount >= out_amount {
+                    break (low, high, high_quote);
+                }
+                low = high;
+                step = step.saturating_mul(2);
+            }
+        };
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            let mid_quote = quote_for(mid)?;
+            if mid_quote.out_amount >= out_amount {
+                high = mid;
+                high_quote = mid_quote;
+            } else {
+                low = mid;
             }
         }
 
-        Ok(())
+        Ok(high_quote)
     }
 
-    fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+    /// Same as `Amm::quote`, but keeps the fee split, weights and weight bounds of the swap.
+    pub fn quote_detailed(&self, quote_params: &QuoteParams) -> Result<SymmetryQuoteBreakdown> {
+
+        let fund_state = self.fund_state();
+        let curve_data = self.curve_data();
 
-        let fund_state = self.fund_state;
-        let token_list = self.token_list;
-        let curve_data = self.curve_data;
-        
-        if fund_state.lp_disabled == FUND_LP_DISABLED {
-            return Err(Error::msg("Manager has disabled liquidity provision on this fund"))
-        }
         let from_amount: u64 = quote_params.in_amount;
-        let from_token_id_option = token_list.list.iter().position(|&x| x.token_mint == quote_params.input_mint);
-        let to_token_id_option = token_list.list.iter().position(|&x| x.token_mint == quote_params.output_mint);
-        
-        if from_token_id_option.is_none() {
-            return Err(Error::msg("From token not found in supported tokens"))
+        if from_amount > SymmetryTokenSwap::MAX_QUOTABLE_AMOUNT {
+            return Err(SymmetryError::AmountTooLarge { amount: from_amount }.into())
         }
-        if to_token_id_option.is_none() {
-            return Err(Error::msg("To token not found in supported tokens"))
-        }
-    
-        let from_token_id: u64 = from_token_id_option.unwrap() as u64;
-        let to_token_id: u64 = to_token_id_option.unwrap() as u64;
-    
-        let from_token_settings = token_list.list[from_token_id as usize];
-        let to_token_settings = token_list.list[to_token_id as usize];
-    
-        let from_token_index_option = fund_state.current_comp_token.iter()
-            .position(|&x| x == (from_token_id as u64));
-        let to_token_index_option = fund_state.current_comp_token.iter()
-            .position(|&x| x == (to_token_id as u64));
-    
-        if from_token_index_option.is_none() {
-            return Err(Error::msg("From token not found in the fund composition"))
-        }
-        if to_token_index_option.is_none() {
-            return Err(Error::msg("To token not found in the fund composition"))
-        }
-
-        let from_token_index: usize = from_token_index_option.unwrap() as usize;
-        let to_token_index: usize = to_token_index_option.unwrap() as usize;
-        
+        let context = self.pair_context(quote_params.input_mint, quote_params.output_mint)?;
+        let PairContext {
+            from_token_id,
+            to_token_id,
+            from_token_index,
+            to_token_index,
+            from_token_settings,
+            to_token_settings,
+            mut fund_worth,
+            from_token_target_amount,
+            to_token_target_amount,
+        } = context;
 
-        let mut fund_worth = 0;
-        for i in 0..(fund_state.num_of_tokens as usize) {
-            let token = fund_state.current_comp_token[i] as usize;
-            let token_settings = token_list.list[token];
-            let token_price = token_settings.oracle_price;
-            if token_price.oracle_live == 0 {
-                return Err(Error::msg("One of the tokens has offline oracle status"))
-            }
-            fund_worth += SymmetryTokenSwap::amount_to_usd_value(
-                fund_state.current_comp_amount[i],
-                token_settings.decimals,
-                token_price.avg_price
-            );
-        }
-    
         let from_token_price = from_token_settings.oracle_price;
         let to_token_price = to_token_settings.oracle_price;
-        
-        let from_token_target_amount: u64 = SymmetryTokenSwap::usd_value_to_amount(
-            SymmetryTokenSwap::mul_div(fund_state.target_weight[from_token_index], fund_worth, fund_state.weight_sum),
-            from_token_settings.decimals,
-            from_token_price.avg_price
-        );
-        let to_token_target_amount: u64 = SymmetryTokenSwap::usd_value_to_amount(
-            SymmetryTokenSwap::mul_div(fund_state.target_weight[to_token_index], fund_worth, fund_state.weight_sum),
-            to_token_settings.decimals,
-            to_token_price.avg_price,
-        );
-    
+
         let value = SymmetryTokenSwap::compute_value_of_sold_token(
             from_amount,
             from_token_settings,
             from_token_price,
-            fund_state.current_comp_amount[from_token_index],
+            fund_state.current_comp_amount(from_token_index),
             from_token_target_amount,
-            curve_data.sell[from_token_id as usize],
+            curve_data.sell(from_token_id as usize),
         );
     
         let mut to_amount = SymmetryTokenSwap::compute_amount_of_bought_token(
             value,
             to_token_settings,
             to_token_price,
-            fund_state.current_comp_amount[to_token_index],
+            fund_state.current_comp_amount(to_token_index),
             to_token_target_amount,
-            curve_data.buy[to_token_id as usize],
+            curve_data.buy(to_token_id as usize),
         );
     
         let mut amount_without_fees = SymmetryTokenSwap::usd_value_to_amount(
@@ -401,24 +633,25 @@ impl Amm for SymmetryTokenSwap {
             to_token_price.avg_price
         );
     
-        if amount_without_fees > fund_state.current_comp_amount[to_token_index] {
-            amount_without_fees = fund_state.current_comp_amount[to_token_index];
+        if amount_without_fees > fund_state.current_comp_amount(to_token_index) {
+            amount_without_fees = fund_state.current_comp_amount(to_token_index);
         }
     
         if to_amount > amount_without_fees {
             to_amount = amount_without_fees
         }
     
+        let marginal_out_amount = self.marginal_out_amount(&context, from_amount);
+        let price_impact_bps = if marginal_out_amount > to_amount {
+            SymmetryTokenSwap::mul_div(marginal_out_amount - to_amount, BPS_DIVIDER * 100, marginal_out_amount)
+        } else { 0 };
+
         let total_fees = amount_without_fees - to_amount;
     
-        let symmetry_bps = token_list.list[0].additional_data[60];
-        let symmetry_fee = SymmetryTokenSwap::mul_div(total_fees, symmetry_bps as u64, 100);
-    
-        let host_bps = token_list.list[0].additional_data[61];
-        let host_fee = SymmetryTokenSwap::mul_div(total_fees, host_bps as u64, 100);
-    
-        let manager_bps = token_list.list[0].additional_data[62];
-        let manager_fee = SymmetryTokenSwap::mul_div(total_fees, manager_bps as u64, 100);
+        let fee_split = self.token_list().fee_split();
+        let symmetry_fee = SymmetryTokenSwap::mul_div(total_fees, fee_split.symmetry_pct as u64, 100);
+        let host_fee = SymmetryTokenSwap::mul_div(total_fees, fee_split.host_pct as u64, 100);
+        let manager_fee = SymmetryTokenSwap::mul_div(total_fees, fee_split.manager_pct as u64, 100);
     
         let fund_fee = total_fees - symmetry_fee - host_fee - manager_fee;
     
@@ -429,28 +662,31 @@ impl Amm for SymmetryTokenSwap {
         );
         
         let from_token_worth_before_swap = SymmetryTokenSwap::amount_to_usd_value(
-            fund_state.current_comp_amount[from_token_index],
+            fund_state.current_comp_amount(from_token_index),
             from_token_settings.decimals,
             from_token_price.avg_price
         );
         let to_token_worth_before_swap = SymmetryTokenSwap::amount_to_usd_value(
-            fund_state.current_comp_amount[to_token_index],
+            fund_state.current_comp_amount(to_token_index),
             to_token_settings.decimals,
             to_token_price.avg_price
         );
     
         let safe_from_amount = from_amount * 101 / 100;
+        let from_amount_after_swap = fund_state.current_comp_amount(from_token_index).checked_add(safe_from_amount)
+            .ok_or(SymmetryError::AmountTooLarge { amount: from_amount })?;
         let from_token_worth_after_swap = SymmetryTokenSwap::amount_to_usd_value(
-            fund_state.current_comp_amount[from_token_index] + safe_from_amount,
+            from_amount_after_swap,
             from_token_settings.decimals,
             from_token_price.avg_price
         );
-        let mut safe_to_amount = (amount_without_fees - fund_fee) * 101 / 100;
-        if safe_to_amount > fund_state.current_comp_amount[to_token_index] {
-            safe_to_amount = fund_state.current_comp_amount[to_token_index];
+        // Capped by the fund's holdings right below, so an overflow only has to saturate
+        let mut safe_to_amount = (amount_without_fees - fund_fee).checked_mul(101).map_or(u64::MAX, |amount| amount / 100);
+        if safe_to_amount > fund_state.current_comp_amount(to_token_index) {
+            safe_to_amount = fund_state.current_comp_amount(to_token_index);
         }
         let to_token_worth_after_swap= SymmetryTokenSwap::amount_to_usd_value(
-            fund_state.current_comp_amount[to_token_index] - safe_to_amount,
+            fund_state.current_comp_amount(to_token_index) - safe_to_amount,
             to_token_settings.decimals,
             to_token_price.avg_price
         );
@@ -471,48 +707,92 @@ impl Amm for SymmetryTokenSwap {
             fund_worth
         );
     
-        let allowed_offset = fund_state.rebalance_threshold * fund_state.lp_offset_threshold;
-    
-        let mut allowed_from_target_weight = SymmetryTokenSwap::mul_div(
-            fund_state.target_weight[from_token_index],
-            BPS_DIVIDER * BPS_DIVIDER + allowed_offset,
-            BPS_DIVIDER * BPS_DIVIDER
-        );
-        let allowed_to_target_weight = SymmetryTokenSwap::mul_div(
-            fund_state.target_weight[to_token_index],
-            BPS_DIVIDER * BPS_DIVIDER - allowed_offset,
-            BPS_DIVIDER * BPS_DIVIDER
-        );
-        if allowed_from_target_weight > WEIGHT_MULTIPLIER {
-            allowed_from_target_weight = WEIGHT_MULTIPLIER;
-        }
+        let (allowed_from_target_weight, _) = SymmetryTokenSwap::allowed_target_weights(&fund_state, from_token_index);
+        let (_, allowed_to_target_weight) = SymmetryTokenSwap::allowed_target_weights(&fund_state, to_token_index);
         
         let removing_dust =
             from_token_id == 0 as u64 &&
-            fund_state.target_weight[to_token_index] == 0;
+            fund_state.target_weight(to_token_index) == 0;
 
         if from_new_weight > allowed_from_target_weight && (!removing_dust) {
-            return Err(Error::msg("From token weight exceeds max allowed weight"))
+            return Err(SymmetryError::WeightAboveMax {
+                new: from_new_weight,
+                allowed: allowed_from_target_weight,
+            }.into())
         }
         
         if to_new_weight < allowed_to_target_weight {
-            return Err(Error::msg("To token weight exceeds min allowed weight"))
+            return Err(SymmetryError::WeightBelowMin {
+                new: to_new_weight,
+                allowed: allowed_to_target_weight,
+            }.into())
         }
 
-        Ok(Quote {
+        Ok(SymmetryQuoteBreakdown {
             in_amount: quote_params.in_amount,
             out_amount: to_amount,
-            fee_amount: total_fees,
-            fee_mint: quote_params.output_mint,
-            fee_pct: Decimal::new(fee_bps as i64, 4),
-            ..Quote::default()
+            fair_amount,
+            amount_without_fees,
+            total_fees,
+            fee_bps,
+            symmetry_fee,
+            host_fee,
+            manager_fee,
+            fund_fee,
+            from_new_weight,
+            to_new_weight,
+            allowed_from_target_weight,
+            allowed_to_target_weight,
+            from_target_weight: fund_state.target_weight(from_token_index),
+            to_target_weight: fund_state.target_weight(to_token_index),
+            marginal_out_amount,
+            price_impact_pct: Decimal::new(price_impact_bps as i64, 4),
         })
     }
 
-    fn get_swap_and_account_metas(
-        &self,
-        swap_params: &SwapParams,
-    ) -> Result<SwapAndAccountMetas> {
+    /// Weight bounds of the token at `index` in the fund composition: the highest weight a swap
+    /// selling it to the fund may leave, and the lowest weight a swap buying it from the fund may leave.
+    fn allowed_target_weights(fund_state: &FundStateView, index: usize) -> (u64, u64) {
+        let allowed_offset = fund_state.rebalance_threshold() * fund_state.lp_offset_threshold();
+
+        let mut allowed_from_target_weight = SymmetryTokenSwap::mul_div(
+            fund_state.target_weight(index),
+            BPS_DIVIDER * BPS_DIVIDER + allowed_offset,
+            BPS_DIVIDER * BPS_DIVIDER
+        );
+        let allowed_to_target_weight = SymmetryTokenSwap::mul_div(
+            fund_state.target_weight(index),
+            (BPS_DIVIDER * BPS_DIVIDER).saturating_sub(allowed_offset),
+            BPS_DIVIDER * BPS_DIVIDER
+        );
+        if allowed_from_target_weight > WEIGHT_MULTIPLIER {
+            allowed_from_target_weight = WEIGHT_MULTIPLIER;
+        }
+        (allowed_from_target_weight, allowed_to_target_weight)
+    }
+
+    /// The `minimum_amount_out` encoded into the swap instruction for the given slippage.
+    /// Slippage in bps is applied to a fresh quote of the swap.
+    pub fn minimum_amount_out(&self, swap_params: &SwapParams, slippage: Slippage) -> Result<u64> {
+        match slippage {
+            Slippage::MinimumAmountOut(minimum_amount_out) => Ok(minimum_amount_out),
+            Slippage::Bps(slippage_bps) => {
+                if slippage_bps > BPS_DIVIDER {
+                    return Err(SymmetryError::InvalidSlippage { bps: slippage_bps }.into())
+                }
+                let quote = self.quote(&QuoteParams {
+                    in_amount: swap_params.in_amount,
+                    input_mint: swap_params.source_mint,
+                    output_mint: swap_params.destination_mint,
+                })?;
+                Ok(SymmetryTokenSwap::mul_div(quote.out_amount, BPS_DIVIDER - slippage_bps, BPS_DIVIDER))
+            }
+        }
+    }
+
+    /// Builds the complete Symmetry swap instruction, for sending swaps without going through Jupiter.
+    /// `token_transfer_authority` signs the instruction; fee token accounts are derived from the fund.
+    pub fn build_swap_instruction(&self, swap_params: &SwapParams, minimum_amount_out: u64) -> Result<Instruction> {
         let SwapParams {
             in_amount,
             source_mint,
@@ -520,23 +800,11 @@ impl Amm for SymmetryTokenSwap {
             source_token_account,
             destination_token_account,
             token_transfer_authority,
-            open_order_address,
-            quote_mint_to_referrer,
-            jupiter_program_id
+            ..
         } = swap_params;
         
-        let from_token_id_option = self.token_list.list.iter().position(|&x| x.token_mint == *source_mint);
-        let to_token_id_option = self.token_list.list.iter().position(|&x| x.token_mint == *destination_mint);
-        
-        if from_token_id_option.is_none() {
-            return Err(Error::msg("From token not found in supported tokens"))
-        }
-        if to_token_id_option.is_none() {
-            return Err(Error::msg("To token not found in supported tokens"))
-        }
-
-        let from_token_id: u64 = from_token_id_option.unwrap() as u64;
-        let to_token_id: u64 = to_token_id_option.unwrap() as u64;
+        let (from_token_id, _) = self.eligible_token(*source_mint)?;
+        let (to_token_id, _) = self.eligible_token(*destination_mint)?;
 
         let swap_to_fee: Pubkey = Pubkey::find_program_address(
             &[
@@ -548,7 +816,7 @@ impl Amm for SymmetryTokenSwap {
         ).0;
         let host_to_fee: Pubkey = Pubkey::find_program_address(
             &[
-                &self.fund_state.host_pubkey.to_bytes(),
+                &self.fund_state().host_pubkey().to_bytes(),
                 &SymmetryTokenSwap::SPL_TOKEN_PROGRAM_ADDRESS.to_bytes(),
                 &destination_mint.to_bytes()
             ], 
@@ -556,7 +824,7 @@ impl Amm for SymmetryTokenSwap {
         ).0;
         let manager_to_fee: Pubkey = Pubkey::find_program_address(
             &[
-                &self.fund_state.manager.to_bytes(),
+                &self.fund_state().manager().to_bytes(),
                 &SymmetryTokenSwap::SPL_TOKEN_PROGRAM_ADDRESS.to_bytes(),
                 &destination_mint.to_bytes()
             ], 
@@ -567,9 +835,9 @@ impl Amm for SymmetryTokenSwap {
         account_metas.push(AccountMeta::new(*token_transfer_authority, true));
         account_metas.push(AccountMeta::new(self.key, false));
         account_metas.push(AccountMeta::new_readonly(SymmetryTokenSwap::PDA_ADDRESS, false));
-        account_metas.push(AccountMeta::new(self.token_list.list[from_token_id as usize].pda_token_account, false));
+        account_metas.push(AccountMeta::new(self.token_settings(from_token_id as usize).pda_token_account, false));
         account_metas.push(AccountMeta::new(*source_token_account, false));
-        account_metas.push(AccountMeta::new(self.token_list.list[to_token_id as usize].pda_token_account, false));
+        account_metas.push(AccountMeta::new(self.token_settings(to_token_id as usize).pda_token_account, false));
         account_metas.push(AccountMeta::new(*destination_token_account, false));
         account_metas.push(AccountMeta::new(swap_to_fee, false));
         account_metas.push(AccountMeta::new(host_to_fee, false));
@@ -579,14 +847,13 @@ impl Amm for SymmetryTokenSwap {
         account_metas.push(AccountMeta::new_readonly(SymmetryTokenSwap::SPL_TOKEN_PROGRAM_ADDRESS, false));
 
         // Pyth Oracle accounts are being passed as remaining accounts
-        for i in 0..self.fund_state.num_of_tokens as usize {
+        for i in 0..self.fund_state().num_of_tokens() as usize {
             account_metas.push(
-                AccountMeta::new_readonly(self.token_list.list[self.fund_state.current_comp_token[i] as usize].oracle_account, false)
+                AccountMeta::new_readonly(self.token_settings(self.fund_state().current_comp_token(i) as usize).oracle_account, false)
             );
         }
 
         let instruction_n: u64 = SymmetryTokenSwap::SYMMETRY_PROGRAM_SWAP_INSTRUCTION_ID;
-        let minimum_amount_out: u64 = 0;
         let mut data = Vec::new();
         data.extend_from_slice(&instruction_n.to_le_bytes());
         data.extend_from_slice(&from_token_id.to_le_bytes());
@@ -594,18 +861,420 @@ impl Amm for SymmetryTokenSwap {
         data.extend_from_slice(&in_amount.to_le_bytes());
         data.extend_from_slice(&minimum_amount_out.to_le_bytes());
     
-        let swap_instruction = Instruction {
+        Ok(Instruction {
             program_id: SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS,
-            accounts: account_metas.clone(),
+            accounts: account_metas,
             data,
+        })
+    }
+
+    /// The largest `in_amount` of `input_mint` that can be swapped to `output_mint` without
+    /// breaking the fund's weight bounds. Output is capped by the fund's holdings of `output_mint`,
+    /// so the result is also limited to the amount that buys out those holdings.
+    pub fn max_in_amount(&self, input_mint: Pubkey, output_mint: Pubkey) -> Result<u64> {
+        let context = self.pair_context(input_mint, output_mint)?;
+        let from_token_price = context.from_token_settings.oracle_price;
+        let to_token_price = context.to_token_settings.oracle_price;
+
+        let available_value = SymmetryTokenSwap::amount_to_usd_value(
+            self.fund_state().current_comp_amount(context.to_token_index),
+            context.to_token_settings.decimals,
+            to_token_price.buy_price
+        );
+        let max_quotable_amount = SymmetryTokenSwap::MAX_QUOTABLE_AMOUNT;
+        let max_amount = SymmetryTokenSwap::mul_div_ceil(
+            available_value,
+            u64::pow(10, context.from_token_settings.decimals as u32),
+            from_token_price.sell_price
+        ).unwrap_or(max_quotable_amount).min(max_quotable_amount);
+
+        let fits_weights = |in_amount: u64| -> Result<bool> {
+            match self.quote_detailed(&QuoteParams { in_amount, input_mint, output_mint }) {
+                Ok(_) => Ok(true),
+                Err(e) => match e.downcast_ref::<SymmetryError>() {
+                    Some(SymmetryError::WeightAboveMax { .. }) | Some(SymmetryError::WeightBelowMin { .. }) => Ok(false),
+                    _ => Err(e),
+                },
+            }
         };
 
+        if !fits_weights(0)? {
+            return Ok(0);
+        }
+        if fits_weights(max_amount)? {
+            return Ok(max_amount);
+        }
+        let (mut low, mut high) = (0, max_amount);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if fits_weights(mid)? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low)
+    }
+
+    /// Quotes every ordered pair of `get_reserve_mints()`, selling `notional_usd` (in `ONE_USD` units)
+    /// worth of the input token at its oracle average price.
+    pub fn quote_matrix(&self, notional_usd: u64) -> SymmetryQuoteMatrix {
+        let mints = self.get_reserve_mints();
+        let quotes = mints.iter().map(|&input_mint| {
+            let in_amount = self.token_list().token_id(input_mint)
+                .map(|token_id| self.token_settings(token_id))
+                .map(|token_settings| SymmetryTokenSwap::usd_value_to_amount(
+                    notional_usd,
+                    token_settings.decimals,
+                    token_settings.oracle_price.avg_price
+                ))
+                .unwrap_or_default();
+            mints.iter().map(|&output_mint| {
+                if output_mint == input_mint {
+                    return None;
+                }
+                Some(SymmetryPairQuote {
+                    input_mint,
+                    output_mint,
+                    in_amount,
+                    result: self.quote_detailed(&QuoteParams { in_amount, input_mint, output_mint }),
+                })
+            }).collect()
+        }).collect();
+
+        SymmetryQuoteMatrix { mints, quotes }
+    }
+
+    /// The oracle or curve price the first unit of a sold token trades at.
+    pub fn marginal_sell_price(
+        token_settings: TokenSettings,
+        price: OraclePrice,
+        start_amount: u64,
+        target_amount: u64,
+        curve_data: TokenPriceData
+    ) -> u64 {
+        let mut curve_offset = start_amount.saturating_sub(target_amount);
+        let mut current_price = price.sell_price;
+        for step in 0..NUM_OF_POINTS_IN_CURVE_DATA {
+            if curve_data.price[step] < current_price && token_settings.use_curve_data == USE_CURVE_DATA {
+                current_price = curve_data.price[step];
+            }
+            if curve_data.amount[step] > curve_offset { break; }
+            curve_offset -= curve_data.amount[step];
+        }
+        current_price
+    }
+
+    /// The oracle or curve price the first unit of a bought token trades at.
+    pub fn marginal_buy_price(
+        token_settings: TokenSettings,
+        price: OraclePrice,
+        start_amount: u64,
+        target_amount: u64,
+        curve_data: TokenPriceData
+    ) -> u64 {
+        let mut curve_offset = target_amount.saturating_sub(start_amount);
+        let mut current_price = price.buy_price;
+        for step in 0..NUM_OF_POINTS_IN_CURVE_DATA {
+            if curve_data.price[step] > current_price && token_settings.use_curve_data == USE_CURVE_DATA {
+                current_price = curve_data.price[step];
+            }
+            if curve_data.amount[step] > curve_offset { break; }
+            curve_offset -= curve_data.amount[step];
+        }
+        current_price
+    }
+
+    /// Output tokens received per input token at zero size, in UI units.
+    pub fn marginal_price(&self, input_mint: Pubkey, output_mint: Pubkey) -> Result<Decimal> {
+        let context = self.pair_context(input_mint, output_mint)?;
+        let one_token = u64::pow(10, context.from_token_settings.decimals as u32);
+        let marginal_out_amount = self.marginal_out_amount(&context, one_token);
+        Ok(Decimal::from(marginal_out_amount) / Decimal::from(u64::pow(10, context.to_token_settings.decimals as u32)))
+    }
+
+    /// Output for `in_amount` at the zero-size prices and fee tiers of both tokens.
+    fn marginal_out_amount(&self, context: &PairContext, in_amount: u64) -> u64 {
+        let from_token_settings = context.from_token_settings;
+        let to_token_settings = context.to_token_settings;
+        let from_start_amount = self.fund_state().current_comp_amount(context.from_token_index);
+        let to_start_amount = self.fund_state().current_comp_amount(context.to_token_index);
+
+        let sell_price = SymmetryTokenSwap::marginal_sell_price(
+            from_token_settings,
+            from_token_settings.oracle_price,
+            from_start_amount,
+            context.from_token_target_amount,
+            self.curve_data().sell(context.from_token_id as usize),
+        );
+        let buy_price = SymmetryTokenSwap::marginal_buy_price(
+            to_token_settings,
+            to_token_settings.oracle_price,
+            to_start_amount,
+            context.to_token_target_amount,
+            self.curve_data().buy(context.to_token_id as usize),
+        );
+        let sell_fee_bps = if from_start_amount < context.from_token_target_amount
+            { from_token_settings.token_swap_fee_before_tw_bps } else { from_token_settings.token_swap_fee_after_tw_bps };
+        let buy_fee_bps = if to_start_amount > context.to_token_target_amount
+            { to_token_settings.token_swap_fee_before_tw_bps } else { to_token_settings.token_swap_fee_after_tw_bps };
+
+        let value = SymmetryTokenSwap::amount_to_usd_value(in_amount, from_token_settings.decimals, sell_price);
+        let value_after_fees = SymmetryTokenSwap::mul_div(
+            SymmetryTokenSwap::mul_div(value, BPS_DIVIDER - sell_fee_bps as u64, BPS_DIVIDER),
+            BPS_DIVIDER - buy_fee_bps as u64,
+            BPS_DIVIDER
+        );
+        SymmetryTokenSwap::usd_value_to_amount(value_after_fees, to_token_settings.decimals, buy_price)
+    }
+
+    /// Per-token amounts, values, weights against targets, oracle status and eligibility of the fund.
+    /// Its `Display` renders a table, meant for finding out why a quote fails.
+    pub fn fund_report(&self) -> SymmetryFundReport {
+        let fund_state = self.fund_state();
+        let token_settings: Vec<TokenSettings> = (0..fund_state.num_of_tokens() as usize)
+            .map(|i| self.token_settings(fund_state.current_comp_token(i) as usize))
+            .collect();
+        let usd_values: Vec<u64> = token_settings.iter().enumerate()
+            .map(|(i, token_settings)| SymmetryTokenSwap::amount_to_usd_value(
+                fund_state.current_comp_amount(i),
+                token_settings.decimals,
+                token_settings.oracle_price.avg_price
+            ))
+            .collect();
+        let fund_worth = usd_values.iter().fold(0u64, |sum, value| sum.saturating_add(*value));
+
+        let tokens = token_settings.iter().enumerate().map(|(i, token_settings)| {
+            let price = token_settings.oracle_price;
+            let current_weight = SymmetryTokenSwap::mul_div(usd_values[i], WEIGHT_MULTIPLIER, fund_worth);
+            let target_weight = SymmetryTokenSwap::mul_div(fund_state.target_weight(i), WEIGHT_MULTIPLIER, fund_state.weight_sum());
+            let (allowed_from_target_weight, allowed_to_target_weight) = SymmetryTokenSwap::allowed_target_weights(&fund_state, i);
+            SymmetryFundReportRow {
+                token_mint: token_settings.token_mint,
+                decimals: token_settings.decimals,
+                amount: fund_state.current_comp_amount(i),
+                usd_value: usd_values[i],
+                current_weight,
+                target_weight,
+                drift_bps: current_weight as i64 - target_weight as i64,
+                allowed_from_target_weight,
+                allowed_to_target_weight,
+                oracle_live: price.oracle_live != 0,
+                spread_bps: SymmetryTokenSwap::mul_div(price.buy_price.saturating_sub(price.sell_price), BPS_DIVIDER, price.avg_price),
+                eligibility_error: self.eligible_token(token_settings.token_mint).err()
+                    .and_then(|e| e.downcast_ref::<SymmetryError>().cloned()),
+            }
+        }).collect();
+
+        SymmetryFundReport {
+            fund: self.key,
+            fund_worth,
+            lp_disabled: fund_state.lp_disabled() == FUND_LP_DISABLED,
+            rebalance_threshold: fund_state.rebalance_threshold(),
+            lp_offset_threshold: fund_state.lp_offset_threshold(),
+            tokens,
+        }
+    }
+
+    /// Checks that `mint` can be swapped in this fund, the error names the reason it can't.
+    pub fn check_token_eligibility(&self, mint: Pubkey) -> Result<()> {
+        self.eligible_token(mint).map(|_| ())
+    }
+
+    /// The eligibility policy shared by `get_reserve_mints`, quoting and swap building.
+    /// Returns the token id in the token list and the token index in the fund.
+    fn eligible_token(&self, mint: Pubkey) -> Result<(u64, usize)> {
+        let fund_state = self.fund_state();
+        let token_id = self.token_list().token_id(mint)
+            .ok_or(SymmetryError::TokenNotSupported { mint })?;
+        let token_index = (0..fund_state.num_of_tokens() as usize)
+            .find(|&i| fund_state.current_comp_token(i) == token_id as u64)
+            .ok_or(SymmetryError::TokenNotInFund { mint })?;
+        let token_settings = self.token_settings(token_id);
+        if token_settings.is_live == TOKEN_NOT_LIVE {
+            return Err(SymmetryError::TokenNotLive { mint }.into())
+        }
+        if token_settings.lp_on == LP_DISABLED {
+            return Err(SymmetryError::TokenLpDisabled { mint }.into())
+        }
+        Ok((token_id as u64, token_index))
+    }
+
+    /// Oracle accounts of the tokens in the fund composition, without duplicates.
+    fn oracle_accounts(&self) -> Vec<Pubkey> {
+        let fund_state = self.fund_state();
+        self.market_snapshot.oracle_accounts((0..fund_state.num_of_tokens() as usize).map(|i| fund_state.current_comp_token(i) as usize))
+    }
+
+    fn pair_context(&self, input_mint: Pubkey, output_mint: Pubkey) -> Result<PairContext> {
+        let fund_state = self.fund_state();
+
+        if fund_state.lp_disabled() == FUND_LP_DISABLED {
+            return Err(SymmetryError::LpDisabled.into())
+        }
+        let (from_token_id, from_token_index) = self.eligible_token(input_mint)?;
+        let (to_token_id, to_token_index) = self.eligible_token(output_mint)?;
+
+        let from_token_settings = self.token_settings(from_token_id as usize);
+        let to_token_settings = self.token_settings(to_token_id as usize);
+
+        let mut fund_worth = 0;
+        for i in 0..(fund_state.num_of_tokens() as usize) {
+            let token = fund_state.current_comp_token(i) as usize;
+            let token_settings = self.token_settings(token);
+            let token_price = token_settings.oracle_price;
+            if token_price.oracle_live == 0 {
+                return Err(SymmetryError::OracleOffline { token: token_settings.token_mint }.into())
+            }
+            fund_worth += SymmetryTokenSwap::amount_to_usd_value(
+                fund_state.current_comp_amount(i),
+                token_settings.decimals,
+                token_price.avg_price
+            );
+        }
+
+        let from_token_target_amount: u64 = SymmetryTokenSwap::usd_value_to_amount(
+            SymmetryTokenSwap::mul_div(fund_state.target_weight(from_token_index), fund_worth, fund_state.weight_sum()),
+            from_token_settings.decimals,
+            from_token_settings.oracle_price.avg_price
+        );
+        let to_token_target_amount: u64 = SymmetryTokenSwap::usd_value_to_amount(
+            SymmetryTokenSwap::mul_div(fund_state.target_weight(to_token_index), fund_worth, fund_state.weight_sum()),
+            to_token_settings.decimals,
+            to_token_settings.oracle_price.avg_price,
+        );
+
+        Ok(PairContext {
+            from_token_id,
+            to_token_id,
+            from_token_index,
+            to_token_index,
+            from_token_settings,
+            to_token_settings,
+            fund_worth,
+            from_token_target_amount,
+            to_token_target_amount,
+        })
+    }
+}
+
+/// Token ids, fund indices and target amounts of a swap pair, shared by the quoting paths.
+#[derive(Clone, Copy)]
+struct PairContext {
+    from_token_id: u64,
+    to_token_id: u64,
+    from_token_index: usize,
+    to_token_index: usize,
+    from_token_settings: TokenSettings,
+    to_token_settings: TokenSettings,
+    fund_worth: u64,
+    from_token_target_amount: u64,
+    to_token_target_amount: u64,
+}
+
+impl Amm for SymmetryTokenSwap {
+
+    /// The token list starts empty and is populated, along with the oracles, by `update`
+    fn from_keyed_account(keyed_account: &KeyedAccount) -> Result<Self> {
+        SymmetryTokenSwap::check_owner("FundState", &keyed_account.account.owner)?;
+        FundStateView::new(&keyed_account.account.data)?;
+
+        Ok(SymmetryTokenSwap::new(
+            keyed_account.key,
+            keyed_account.account.data.as_slice().into(),
+            Arc::new(SymmetryMarket::new()),
+            false
+        ))
+    }
+
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn key(&self) -> Pubkey {
+        self.key
+    }
+
+    fn get_reserve_mints(&self) -> Vec<Pubkey> {
+        let mut vec: Vec<Pubkey> = Vec::new();
+        for i in 0..self.fund_state().num_of_tokens() as usize {
+            let token_mint = self.token_settings(self.fund_state().current_comp_token(i) as usize).token_mint;
+            if self.eligible_token(token_mint).is_ok() {
+                vec.push(token_mint)
+            }
+        }
+        vec
+    }
+
+    /// Funds of a shared `SymmetryMarket` only need their fund state, the market fetches the rest
+    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        if self.shares_market {
+            return vec![self.key];
+        }
+        let mut accounts_to_update = vec![
+            SymmetryTokenSwap::CURVE_DATA_ADDRESS,
+            self.key,
+            SymmetryTokenSwap::TOKEN_LIST_ADDRESS,
+            clock::ID,
+        ];
+        accounts_to_update.extend(self.oracle_accounts());
+        accounts_to_update
+    }
+
+    fn update(&mut self, account_map: &AccountMap) -> Result<()> {
+        let fund_state = SymmetryTokenSwap::try_get_program_account_data(account_map, &self.key, "FundState")?;
+        let new_fund_state = FundStateView::new(fund_state)?;
+
+        if !self.shares_market {
+            // Oracles of tokens that just entered the fund (or of the first token list) were not requested
+            // by `get_accounts_to_update` yet, they stay unpriced until the next update
+            let fund_tokens = (0..new_fund_state.num_of_tokens() as usize).map(|i| new_fund_state.current_comp_token(i) as usize);
+            self.market.update_tokens(account_map, fund_tokens)?;
+        }
+        self.fund_state = fund_state.into();
+        self.market_snapshot = self.market.snapshot();
+
+        Ok(())
+    }
+
+    /// The `Quote` of jupiter-amm-interface 0.2 has no price impact field,
+    /// `quote_detailed` reports it as `price_impact_pct`.
+    fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let breakdown = self.quote_detailed(quote_params)?;
+
+        Ok(Quote {
+            in_amount: breakdown.in_amount,
+            out_amount: breakdown.out_amount,
+            fee_amount: breakdown.total_fees,
+            fee_mint: quote_params.output_mint,
+            fee_pct: Decimal::new(breakdown.fee_bps as i64, 4),
+            ..Quote::default()
+        })
+    }
+
+    /// Only the account metas reach the router, which enforces its own slippage on the swap.
+    /// Use `build_swap_instruction` to encode a `minimum_amount_out` into the Symmetry instruction.
+    fn get_swap_and_account_metas(
+        &self,
+        swap_params: &SwapParams,
+    ) -> Result<SwapAndAccountMetas> {
+        let swap_instruction = self.build_swap_instruction(swap_params, 0)?;
+
         Ok(SwapAndAccountMetas {
             swap: Swap::TokenSwap,
-            account_metas,
+            account_metas: swap_instruction.accounts,
         })
     }
 
+    fn has_dynamic_accounts(&self) -> bool {
+        true
+    }
+
     fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
         Box::new(self.clone())
     }
@@ -635,6 +1304,12 @@ fn test_symetry_token_swap() {
     /* Update TokenSwap (FundState + CurveData + Pyth Oracle accounts) */
     test_harness.update_amm(&mut token_swap);
 
+    /* Jupiter-facing constructor: the first update loads the token list, the second its oracles */
+    let mut amm = <SymmetryTokenSwap as Amm>::from_keyed_account(&fund_state_account).unwrap();
+    test_harness.update_amm(&mut amm);
+    test_harness.update_amm(&mut amm);
+    assert_eq!(amm.get_reserve_mints(), token_swap.get_reserve_mints());
+
     /* Token mints available for swap in a fund */
     println!("-------------------");
     let token_mints = token_swap.get_reserve_mints();
@@ -653,7 +1328,7 @@ fn test_symetry_token_swap() {
         })
         .unwrap();
     println!("Quote result: {:?}", quote);
-    
+
     /* Get swap and account metas */
     println!("------------");
     let user = Pubkey::new_unique();
@@ -673,7 +1348,7 @@ fn test_symetry_token_swap() {
         ], 
         &SymmetryTokenSwap::ASSOCIATED_TOKEN_PROGRAM_ADDRESS
     ).0;
-    let swap_and_account_metas = token_swap.get_swap_and_account_metas(&SwapParams {
+    let swap_params = SwapParams {
         in_amount: in_amount,
         source_mint: from_token_mint, 
         destination_mint: to_token_mint,
@@ -683,5 +1358,442 @@ fn test_symetry_token_swap() {
         open_order_address: Option::None,
         quote_mint_to_referrer: Option::None,
         jupiter_program_id: &Pubkey::default(),
+    };
+    let swap_and_account_metas = token_swap.get_swap_and_account_metas(&swap_params).unwrap();
+
+    /* Build the swap instruction directly */
+    let minimum_amount_out = token_swap.minimum_amount_out(&swap_params, Slippage::Bps(50)).unwrap();
+    let swap_instruction = token_swap.build_swap_instruction(&swap_params, minimum_amount_out).unwrap();
+    assert_eq!(swap_instruction.accounts, swap_and_account_metas.account_metas);
+}
+
+#[test]
+fn test_symmetry_exact_out_inverts_curves() {
+    use crate::amms::accounts::ONE_USD;
+
+    let token_settings = TokenSettings {
+        token_mint: Pubkey::new_unique(),
+        decimals: 9,
+        coingecko_id: [0; 30],
+        pda_token_account: Pubkey::new_unique(),
+        oracle_type: 0,
+        oracle_account: Pubkey::new_unique(),
+        oracle_index: 0,
+        oracle_confidence_pct: 0,
+        fixed_confidence_bps: 0,
+        token_swap_fee_after_tw_bps: 30,
+        token_swap_fee_before_tw_bps: 10,
+        is_live: 1,
+        lp_on: 1,
+        use_curve_data: USE_CURVE_DATA,
+        additional_data: [0; 63],
+        oracle_price: OraclePrice { sell_price: 99 * ONE_USD, avg_price: 100 * ONE_USD, buy_price: 101 * ONE_USD, oracle_live: 1 },
+    };
+    let sell_curve = TokenPriceData {
+        amount: [2_000_000_000; NUM_OF_POINTS_IN_CURVE_DATA],
+        price: [98, 97, 96, 95, 94, 93, 92, 91, 90, 89].map(|p| p * ONE_USD),
+    };
+    let buy_curve = TokenPriceData {
+        amount: [2_000_000_000; NUM_OF_POINTS_IN_CURVE_DATA],
+        price: [102, 103, 104, 105, 106, 107, 108, 109, 110, 111].map(|p| p * ONE_USD),
+    };
+    let price = token_settings.oracle_price;
+    let (start_amount, target_amount) = (5_000_000_000, 8_000_000_000);
+
+    for value in [1, ONE_USD, 350 * ONE_USD, 1_234 * ONE_USD + 17, 5_000 * ONE_USD] {
+        let amount = SymmetryTokenSwap::compute_amount_of_sold_token(
+            value, token_settings, price, start_amount, target_amount, sell_curve
+        ).unwrap();
+        let received = SymmetryTokenSwap::compute_value_of_sold_token(
+            amount, token_settings, price, start_amount, target_amount, sell_curve
+        );
+        assert!(received >= value, "sold {} for {} < {}", amount, received, value);
+        assert!(received - value <= 100 * ONE_USD / 1_000_000_000 + 1);
+    }
+
+    for amount in [1, 1_000_000, 1_500_000_000, 9_999_999_999, 25_000_000_000] {
+        let value = SymmetryTokenSwap::compute_value_of_bought_token(
+            amount, token_settings, price, target_amount, start_amount, buy_curve
+        ).unwrap();
+        let bought = SymmetryTokenSwap::compute_amount_of_bought_token(
+            value, token_settings, price, target_amount, start_amount, buy_curve
+        );
+        assert!(bought >= amount, "paid {} for {} < {}", value, bought, amount);
+        assert!(bought - amount <= 2);
+    }
+}
+
+/// Encoded accounts of a two token fund (9 decimals at $20 and 6 decimals at $1, $2000 of each,
+/// targets 60/40) and its oracles, with the fund key.
+#[cfg(test)]
+pub(crate) fn test_fund_accounts() -> (Pubkey, AccountMap) {
+    use crate::amms::accounts::{CurveData, FundState, TokenList, NUM_TOKENS_IN_FUND};
+    use crate::amms::oracles::pyth::{PythPriceAccount, PythPriceStatus};
+    use solana_sdk::account::Account;
+    use std::collections::HashMap;
+
+    let account = |data: Vec<u8>, owner: Pubkey| Account { lamports: 1, data, owner, executable: false, rent_epoch: 0 };
+    let oracle = |price: i64, conf: u64| PythPriceAccount {
+        expo: -8,
+        valid_slot: 1_000,
+        ema_price: price,
+        ema_conf: conf,
+        timestamp: 0,
+        price,
+        conf,
+        status: PythPriceStatus::Trading,
+        publish_slot: 1_000,
+    }.to_account_data();
+
+    let mut token_list = TokenList::empty();
+    token_list.num_tokens = 2;
+    for (token, decimals) in [(0, 9), (1, 6)] {
+        let token_settings = &mut token_list.list[token];
+        token_settings.token_mint = Pubkey::new_unique();
+        token_settings.decimals = decimals;
+        token_settings.pda_token_account = Pubkey::new_unique();
+        token_settings.oracle_account = Pubkey::new_unique();
+        token_settings.oracle_confidence_pct = 100;
+        token_settings.token_swap_fee_after_tw_bps = 30;
+        token_settings.token_swap_fee_before_tw_bps = 10;
+        token_settings.is_live = 1;
+        token_settings.lp_on = 1;
+    }
+    // Symmetry, host and manager take 20%, 30% and 10% of the fees, the fund keeps the rest
+    token_list.list[0].additional_data[60..63].copy_from_slice(&[20, 30, 10]);
+    let mut fund_state = FundState {
+        manager: Pubkey::new_unique(),
+        host_pubkey: Pubkey::new_unique(),
+        num_of_tokens: 2,
+        current_comp_token: [0; NUM_TOKENS_IN_FUND],
+        current_comp_amount: [0; NUM_TOKENS_IN_FUND],
+        target_weight: [0; NUM_TOKENS_IN_FUND],
+        weight_sum: 10000,
+        rebalance_threshold: 500,
+        lp_offset_threshold: 2,
+        lp_disabled: 0,
+    };
+    fund_state.current_comp_token[1] = 1;
+    fund_state.current_comp_amount[0] = 100_000_000_000;
+    fund_state.current_comp_amount[1] = 2_000_000_000;
+    fund_state.target_weight[0] = 6000;
+    fund_state.target_weight[1] = 4000;
+
+    let key = Pubkey::new_unique();
+    let program = SymmetryTokenSwap::SYMMETRY_PROGRAM_ADDRESS;
+    let mut account_map: AccountMap = HashMap::new();
+    account_map.insert(key, account(fund_state.to_account_data(), program));
+    account_map.insert(SymmetryTokenSwap::TOKEN_LIST_ADDRESS, account(token_list.to_account_data(), program));
+    account_map.insert(SymmetryTokenSwap::CURVE_DATA_ADDRESS, account(CurveData::empty().to_account_data(), program));
+    account_map.insert(token_list.list[0].oracle_account, account(oracle(2_000_000_000, 1_000_000), Pubkey::default()));
+    account_map.insert(token_list.list[1].oracle_account, account(oracle(100_000_000, 10_000), Pubkey::default()));
+    (key, account_map)
+}
+
+/// The fund of `test_fund_accounts`, updated offline.
+#[cfg(test)]
+fn test_fund() -> SymmetryTokenSwap {
+    let (key, account_map) = test_fund_accounts();
+    test_fund_from_accounts(key, &account_map)
+}
+
+/// The fund of `test_fund_accounts`, with the first token sold along a curve falling
+/// $0.10 per token from $19.90 instead of at its oracle price.
+#[cfg(test)]
+fn test_fund_with_sell_curve() -> SymmetryTokenSwap {
+    use crate::amms::accounts::{CurveData, TokenList, ONE_USD};
+
+    let (key, mut account_map) = test_fund_accounts();
+    let token_list_account = account_map.get_mut(&SymmetryTokenSwap::TOKEN_LIST_ADDRESS).unwrap();
+    let mut token_list = TokenList::load(&token_list_account.data).unwrap();
+    token_list.list[0].use_curve_data = USE_CURVE_DATA;
+    token_list_account.data = token_list.to_account_data();
+    let mut curve_data = CurveData::empty();
+    curve_data.sell[0] = TokenPriceData {
+        amount: [1_000_000_000; NUM_OF_POINTS_IN_CURVE_DATA],
+        price: [199, 198, 197, 196, 195, 194, 193, 192, 191, 190].map(|p| p * ONE_USD / 10),
+    };
+    account_map.get_mut(&SymmetryTokenSwap::CURVE_DATA_ADDRESS).unwrap().data = curve_data.to_account_data();
+    test_fund_from_accounts(key, &account_map)
+}
+
+#[cfg(test)]
+fn test_fund_from_accounts(key: Pubkey, account_map: &AccountMap) -> SymmetryTokenSwap {
+    let mut token_swap = <SymmetryTokenSwap as Amm>::from_keyed_account(&KeyedAccount {
+        key,
+        account: account_map[&key].clone(),
+        params: None,
     }).unwrap();
+    token_swap.set_clock_override(Some(Clock { slot: 1_000, ..Clock::default() }));
+    token_swap.update(account_map).unwrap();
+    token_swap
+}
+
+#[test]
+fn test_update_after_a_token_enters_the_fund() {
+    use crate::amms::accounts::{FundState, TokenList};
+
+    // A third listed token, not in the fund yet
+    let (key, mut account_map) = test_fund_accounts();
+    let token_list_account = account_map.get_mut(&SymmetryTokenSwap::TOKEN_LIST_ADDRESS).unwrap();
+    let mut token_list = TokenList::load(&token_list_account.data).unwrap();
+    token_list.num_tokens = 3;
+    token_list.list[2] = token_list.list[1];
+    token_list.list[2].token_mint = Pubkey::new_unique();
+    token_list.list[2].oracle_account = Pubkey::new_unique();
+    token_list_account.data = token_list.to_account_data();
+    let new_oracle = token_list.list[2].oracle_account;
+    let new_oracle_account = account_map[&token_list.list[1].oracle_account].clone();
+
+    let mut token_swap = test_fund_from_accounts(key, &account_map);
+    let mints = token_swap.get_reserve_mints();
+    let quote_params = QuoteParams { in_amount: 1_000_000_000, input_mint: mints[0], output_mint: mints[1] };
+    assert!(!token_swap.get_accounts_to_update().contains(&new_oracle));
+
+    // The token enters the fund between `get_accounts_to_update` and `update`
+    let mut fund_state = FundState::load(&account_map[&key].data).unwrap();
+    fund_state.num_of_tokens = 3;
+    fund_state.current_comp_token[2] = 2;
+    fund_state.current_comp_amount[2] = 1_000_000;
+    account_map.get_mut(&key).unwrap().data = fund_state.to_account_data();
+    token_swap.update(&account_map).unwrap();
+    let report = token_swap.fund_report();
+    assert_eq!(report.tokens.len(), 3);
+    assert!(!report.tokens[2].oracle_live);
+    let error = token_swap.quote(&quote_params).unwrap_err();
+    assert_eq!(error.downcast_ref::<SymmetryError>(), Some(&SymmetryError::OracleOffline { token: token_list.list[2].token_mint }));
+
+    // Its oracle is requested and priced by the next update
+    assert!(token_swap.get_accounts_to_update().contains(&new_oracle));
+    account_map.insert(new_oracle, new_oracle_account);
+    token_swap.update(&account_map).unwrap();
+    assert!(token_swap.fund_report().tokens[2].oracle_live);
+    assert!(token_swap.quote(&quote_params).is_ok());
+
+    // A failed update keeps the fund state and market of the last successful one
+    let report = token_swap.fund_report();
+    fund_state.current_comp_amount[0] = 1;
+    account_map.get_mut(&key).unwrap().data = fund_state.to_account_data();
+    account_map.remove(&SymmetryTokenSwap::CURVE_DATA_ADDRESS);
+    assert!(token_swap.update(&account_map).is_err());
+    assert_eq!(token_swap.fund_report(), report);
+}
+
+#[test]
+fn test_fund_report() {
+    use crate::amms::accounts::{FundState, ONE_USD};
+
+    let token_swap = test_fund();
+    let report = token_swap.fund_report();
+    assert_eq!(report.fund_worth, 4_000 * ONE_USD);
+    assert!(!report.lp_disabled);
+    assert_eq!(report.tokens.len(), 2);
+    assert_eq!(report.tokens[0].usd_value, 2_000 * ONE_USD);
+    assert_eq!(report.tokens[0].current_weight, 5000);
+    assert_eq!(report.tokens[0].target_weight, 6000);
+    assert_eq!(report.tokens[0].drift_bps, -1000);
+    assert_eq!(report.tokens[1].drift_bps, 1000);
+    assert!(report.tokens[1].oracle_live);
+    assert_eq!(report.tokens[1].spread_bps, 2);
+    assert_eq!(report.tokens[1].eligibility_error, None);
+
+    // Rows carry the same weight bounds quotes enforce
+    let mints = token_swap.get_reserve_mints();
+    let breakdown = token_swap.quote_detailed(&QuoteParams { in_amount: 1_000_000_000, input_mint: mints[0], output_mint: mints[1] }).unwrap();
+    assert_eq!(report.tokens[0].allowed_from_target_weight, breakdown.allowed_from_target_weight);
+    assert_eq!(report.tokens[1].allowed_to_target_weight, breakdown.allowed_to_target_weight);
+    assert_eq!((report.tokens[0].allowed_to_target_weight, report.tokens[0].allowed_from_target_weight), (5999, 6000));
+
+    let table = report.to_string();
+    assert!(table.contains("worth $4000.00"));
+    assert!(table.contains(&report.tokens[0].token_mint.to_string()));
+    assert!(table.contains("!-1000"));
+    assert!(table.contains("!1000"));
+
+    let (key, account_map) = test_fund_accounts();
+    let fund_with = |amounts: [u64; 2], weight_sum: u64| {
+        let mut fund_state = FundState::load(&account_map[&key].data).unwrap();
+        fund_state.current_comp_amount[..2].copy_from_slice(&amounts);
+        fund_state.weight_sum = weight_sum;
+        let mut account_map = account_map.clone();
+        account_map.get_mut(&key).unwrap().data = fund_state.to_account_data();
+        test_fund_from_accounts(key, &account_map)
+    };
+
+    // Targets are scaled by the weight sum, drift is in bps of the fund worth
+    let scaled = fund_with([100_000_000_000, 2_000_000_000], 20000).fund_report();
+    assert_eq!((scaled.tokens[0].target_weight, scaled.tokens[1].target_weight), (3000, 2000));
+    assert_eq!((scaled.tokens[0].drift_bps, scaled.tokens[1].drift_bps), (2000, 3000));
+
+    // A drift within the rebalance threshold is not flagged, even outside the quote bounds
+    let drifted = fund_with([118_000_000_000, 1_640_000_000], 10000);
+    let report = drifted.fund_report();
+    assert_eq!((report.tokens[0].current_weight, report.tokens[0].drift_bps), (5900, -100));
+    assert_eq!((report.tokens[1].current_weight, report.tokens[1].drift_bps), (4100, 100));
+    assert!(report.tokens[1].current_weight > report.tokens[1].allowed_from_target_weight);
+    assert!(!report.to_string().contains('!'));
+    let drifted_mints = drifted.get_reserve_mints();
+    assert_eq!(drifted.max_in_amount(drifted_mints[1], drifted_mints[0]).unwrap(), 0);
+
+    // Past the 500 bps rebalance threshold it is
+    let report = fund_with([108_000_000_000, 1_840_000_000], 10000).fund_report();
+    assert_eq!((report.tokens[0].drift_bps, report.tokens[1].drift_bps), (-600, 600));
+    let table = report.to_string();
+    assert!(table.contains("!-600"));
+    assert!(table.contains("!600"));
+}
+
+#[test]
+fn test_build_swap_instruction_encodes_minimum_amount_out() {
+    let token_swap = test_fund();
+    let mints = token_swap.get_reserve_mints();
+    let swap_params = SwapParams {
+        in_amount: 1_000_000_000,
+        source_mint: mints[0],
+        destination_mint: mints[1],
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+        token_transfer_authority: Pubkey::new_unique(),
+        open_order_address: None,
+        quote_mint_to_referrer: None,
+        jupiter_program_id: &Pubkey::default(),
+    };
+    let encoded_minimum_amount_out = |slippage: Slippage| {
+        let minimum_amount_out = token_swap.minimum_amount_out(&swap_params, slippage).unwrap();
+        let data = token_swap.build_swap_instruction(&swap_params, minimum_amount_out).unwrap().data;
+        assert_eq!(data.len(), 40);
+        assert_eq!(u64::from_le_bytes(data[24..32].try_into().unwrap()), swap_params.in_amount);
+        u64::from_le_bytes(data[32..40].try_into().unwrap())
+    };
+
+    let out_amount = token_swap.quote(&QuoteParams {
+        in_amount: swap_params.in_amount,
+        input_mint: mints[0],
+        output_mint: mints[1],
+    }).unwrap().out_amount;
+    assert_eq!(encoded_minimum_amount_out(Slippage::Bps(50)), out_amount * 9950 / 10000);
+    assert_eq!(encoded_minimum_amount_out(Slippage::Bps(0)), out_amount);
+    assert_eq!(encoded_minimum_amount_out(Slippage::MinimumAmountOut(12_345)), 12_345);
+
+    let error = token_swap.minimum_amount_out(&swap_params, Slippage::Bps(10_001)).unwrap_err();
+    assert_eq!(error.downcast_ref::<SymmetryError>(), Some(&SymmetryError::InvalidSlippage { bps: 10_001 }));
+}
+
+#[test]
+fn test_quote_reports_price_impact() {
+    let quote_with_impact = |token_swap: &SymmetryTokenSwap, in_amount: u64| {
+        let mints = token_swap.get_reserve_mints();
+        let quote = token_swap.quote_detailed(&QuoteParams { in_amount, input_mint: mints[0], output_mint: mints[1] }).unwrap();
+        quote.price_impact_pct
+    };
+
+    let flat = test_fund();
+    assert_eq!(quote_with_impact(&flat, 5_000_000_000), Decimal::ZERO);
+
+    let curved = test_fund_with_sell_curve();
+    assert_eq!(quote_with_impact(&curved, 500_000_000), Decimal::ZERO);
+    let small_impact = quote_with_impact(&curved, 2_000_000_000);
+    let large_impact = quote_with_impact(&curved, 5_000_000_000);
+    assert!(small_impact > Decimal::ZERO);
+    assert!(large_impact > small_impact, "{} <= {}", large_impact, small_impact);
+}
+
+#[test]
+fn test_quote_exact_out_finds_smallest_in_amount() {
+    let token_swap = test_fund();
+    let mints = token_swap.get_reserve_mints();
+    let quote_for = |in_amount: u64| token_swap.quote(&QuoteParams { in_amount, input_mint: mints[0], output_mint: mints[1] });
+
+    for out_amount in [1, 1_000, 19_000_000, 123_456_789, 300_000_000] {
+        let quote = token_swap.quote_exact_out(mints[0], mints[1], out_amount).unwrap();
+        assert!(quote.out_amount >= out_amount, "{} < {}", quote.out_amount, out_amount);
+        assert!(quote_for(quote.in_amount - 1).unwrap().out_amount < out_amount, "{} is not the smallest input", quote.in_amount);
+    }
+
+    let error = token_swap.quote_exact_out(mints[0], mints[1], 2_000_000_001).unwrap_err();
+    assert!(matches!(error.downcast_ref::<SymmetryError>(), Some(SymmetryError::NotEnoughLiquidity { .. })));
+}
+
+#[test]
+fn test_quote_rejects_huge_amounts_without_panicking() {
+    let token_swap = test_fund();
+    let mints = token_swap.get_reserve_mints();
+    for in_amount in [u64::MAX / 101 + 1, u64::MAX / 50, u64::MAX] {
+        let error = token_swap.quote(&QuoteParams { in_amount, input_mint: mints[0], output_mint: mints[1] }).unwrap_err();
+        assert_eq!(error.downcast_ref::<SymmetryError>(), Some(&SymmetryError::AmountTooLarge { amount: in_amount }));
+    }
+}
+
+#[test]
+fn test_max_in_amount_is_the_weight_bound() {
+    let token_swap = test_fund();
+    let mints = token_swap.get_reserve_mints();
+    let quote_for = |in_amount: u64| token_swap.quote(&QuoteParams { in_amount, input_mint: mints[0], output_mint: mints[1] });
+
+    let max_in_amount = token_swap.max_in_amount(mints[0], mints[1]).unwrap();
+    assert!(max_in_amount > 0);
+    assert!(quote_for(max_in_amount).is_ok());
+    let error = quote_for(max_in_amount + 1).unwrap_err();
+    assert!(matches!(error.downcast_ref::<SymmetryError>(), Some(SymmetryError::WeightAboveMax { .. })), "{}", error);
+
+    // The second token is already above its target weight, so none of it can be sold
+    assert_eq!(token_swap.max_in_amount(mints[1], mints[0]).unwrap(), 0);
+}
+
+#[test]
+fn test_quote_matrix() {
+    use crate::amms::accounts::ONE_USD;
+
+    let token_swap = test_fund();
+    let matrix = token_swap.quote_matrix(100 * ONE_USD);
+    assert_eq!(matrix.mints, token_swap.get_reserve_mints());
+    assert_eq!(matrix.quotes.len(), 2);
+    for (i, row) in matrix.quotes.iter().enumerate() {
+        assert_eq!(row.len(), 2);
+        assert!(row[i].is_none());
+        let in_amount = SymmetryTokenSwap::usd_value_to_amount(
+            100 * ONE_USD,
+            token_swap.token_settings(i).decimals,
+            token_swap.token_settings(i).oracle_price.avg_price
+        );
+        let pair_quote = row[1 - i].as_ref().unwrap();
+        assert_eq!((pair_quote.input_mint, pair_quote.output_mint), (matrix.mints[i], matrix.mints[1 - i]));
+        assert_eq!(pair_quote.in_amount, in_amount);
+    }
+    assert_eq!(matrix.quotes[0][1].as_ref().unwrap().in_amount, 5_000_000_000);
+    assert_eq!(matrix.quotes[1][0].as_ref().unwrap().in_amount, 100_000_000);
+
+    assert!(matrix.quotes[0][1].as_ref().unwrap().result.as_ref().unwrap().out_amount > 0);
+    // Selling the token already above its target weight is infeasible
+    let error = matrix.quotes[1][0].as_ref().unwrap().result.as_ref().unwrap_err();
+    assert!(matches!(error.downcast_ref::<SymmetryError>(), Some(SymmetryError::WeightAboveMax { .. })), "{}", error);
+}
+
+#[test]
+fn test_quote_detailed_breakdown() {
+    let token_swap = test_fund();
+    let mints = token_swap.get_reserve_mints();
+    let breakdown = token_swap.quote_detailed(&QuoteParams {
+        in_amount: 5_000_000_000,
+        input_mint: mints[0],
+        output_mint: mints[1],
+    }).unwrap();
+
+    assert!(breakdown.total_fees > 0);
+    assert_eq!(breakdown.symmetry_fee, breakdown.total_fees * 20 / 100);
+    assert_eq!(breakdown.host_fee, breakdown.total_fees * 30 / 100);
+    assert_eq!(breakdown.manager_fee, breakdown.total_fees * 10 / 100);
+    assert_eq!(breakdown.symmetry_fee + breakdown.host_fee + breakdown.manager_fee + breakdown.fund_fee, breakdown.total_fees);
+    assert_eq!(breakdown.out_amount + breakdown.total_fees, breakdown.amount_without_fees);
+    assert!(breakdown.amount_without_fees <= breakdown.fair_amount);
+
+    let fund_state = token_swap.fund_state();
+    let allowed_offset = fund_state.rebalance_threshold() * fund_state.lp_offset_threshold();
+    assert_eq!(allowed_offset, 1000);
+    assert_eq!((breakdown.from_target_weight, breakdown.to_target_weight), (6000, 4000));
+    assert_eq!(breakdown.allowed_from_target_weight, 6000 * (BPS_DIVIDER * BPS_DIVIDER + allowed_offset) / (BPS_DIVIDER * BPS_DIVIDER));
+    assert_eq!(breakdown.allowed_to_target_weight, 4000 * (BPS_DIVIDER * BPS_DIVIDER - allowed_offset) / (BPS_DIVIDER * BPS_DIVIDER));
+    assert_eq!((breakdown.allowed_from_target_weight, breakdown.allowed_to_target_weight), (6000, 3999));
+    assert!(breakdown.from_new_weight > 5000 && breakdown.from_new_weight <= breakdown.allowed_from_target_weight);
+    assert!(breakdown.to_new_weight < 5000 && breakdown.to_new_weight >= breakdown.allowed_to_target_weight);
 }
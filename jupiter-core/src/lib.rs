@@ -1,4 +1,4 @@
-mod amms;
+pub mod amms;
 mod math;
 
 pub mod config;